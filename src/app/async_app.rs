@@ -0,0 +1,303 @@
+
+use std::{future::{Future, poll_fn}, pin::Pin, rc::Rc, cell::RefCell, collections::VecDeque, marker::PhantomData, mem};
+use std::sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}};
+use std::task::{Context, Poll, Wake, Waker};
+use winit::{window::WindowId, event::DeviceEvent};
+use crate::*;
+use super::{AppHandler, AppEvent, AppCtx, WindowCtx, BoxFuture};
+
+
+// app as a single future, which awaits the next event
+
+pub trait AsyncAppHandler<T: 'static = ()>: Sized + 'static {
+
+  type InitData;
+
+  fn run(ctx: AsyncCtx<T>, init_data: Self::InitData) -> impl Future<Output=()>;
+}
+
+
+// events queued while the app awaits something else, the oldest are dropped beyond this
+const MAX_QUEUED: usize = 1024;
+
+struct Shared<T: 'static> {
+  events: VecDeque<AppEvent<T>>,
+  waiting: Option<Waker>,
+  overflow: bool,
+  app_ctx: Option<AppCtx<T>>,
+}
+
+impl<T: 'static> Shared<T> {
+
+  fn pop(&mut self) -> Option<AppEvent<T>> {
+    let event = self.events.pop_front();
+    if self.events.is_empty() { self.overflow = false }
+    event
+  }
+}
+
+impl<T: Clone + 'static> Shared<T> {
+
+  fn push(&mut self, event: &AppEvent<T>) {
+
+    if let Some(waker) = self.waiting.take() {
+      self.events.push_back(event.clone());
+      waker.wake();
+      return;
+    }
+
+    // nobody awaits events, coalesce the ones sent on every loop iteration
+    // mouse motion deltas of the same device add up, other device events are queued as they are
+    match event {
+      AppEvent::AboutToWait if self.events.iter().any(|queued| matches!(queued, AppEvent::AboutToWait)) => return,
+      AppEvent::DeviceEvent { device_id, event: DeviceEvent::MouseMotion { delta } } => {
+        let last = self.events.iter_mut().rev().find(|queued| !matches!(queued, AppEvent::AboutToWait));
+        if let Some(AppEvent::DeviceEvent { device_id: last_id, event: DeviceEvent::MouseMotion { delta: last } }) = last {
+          if last_id == device_id {
+            (last.0, last.1) = (last.0 + delta.0, last.1 + delta.1);
+            return;
+          }
+        }
+      },
+      _ => {},
+    }
+
+    if self.events.len() >= MAX_QUEUED {
+      if !self.overflow { log_warn!("AsyncCtx event queue is full, dropping the oldest events") }
+      self.overflow = true;
+      self.events.pop_front();
+    }
+
+    self.events.push_back(event.clone());
+  }
+}
+
+pub struct AsyncCtx<T: 'static = ()> {
+  shared: Rc<RefCell<Shared<T>>>,
+}
+
+impl<T: 'static> Clone for AsyncCtx<T> {
+  fn clone(&self) -> Self { Self { shared: Rc::clone(&self.shared) } }
+}
+
+impl<T: 'static> AsyncCtx<T> {
+
+  pub async fn next_event(&self) -> AppEvent<T> {
+    poll_fn(|cx| {
+      let mut shared = self.shared.borrow_mut();
+      match shared.pop() {
+        Some(event) => {
+          shared.waiting = None;
+          Poll::Ready(event)
+        },
+        None => {
+          shared.waiting = Some(cx.waker().clone());
+          Poll::Pending
+        },
+      }
+    }).await
+  }
+
+  pub fn try_next_event(&self) -> Option<AppEvent<T>> {
+    self.shared.borrow_mut().pop()
+  }
+
+  // AppCtx is accessible while the app future is polled by the AppMount, panics otherwise
+  pub fn with<R>(&self, func: impl FnOnce(&mut AppCtx<T>) -> R) -> R {
+    let mut app_ctx = self.shared.borrow_mut().app_ctx.take().expect("AppCtx is not accessible outside of app polling");
+    let res = func(&mut app_ctx);
+    self.shared.borrow_mut().app_ctx = Some(app_ctx);
+    res
+  }
+}
+
+
+// wakes the app through the event loop
+
+#[derive(Default)]
+struct Ping {
+  pinged: AtomicBool,
+  waker: Mutex<Option<Waker>>,
+}
+
+impl Wake for Ping {
+  fn wake(self: Arc<Self>) { self.wake_by_ref() }
+  fn wake_by_ref(self: &Arc<Self>) {
+    self.pinged.store(true, Ordering::Release);
+    if let Some(waker) = self.waker.lock().unwrap().take() { waker.wake() }
+  }
+}
+
+fn spawn_pinger<T: 'static>(ping: Arc<Ping>, event_loop_proxy: PlatformEventLoopProxy<T>) {
+  spawn_local(async move {
+    loop {
+      poll_fn(|cx| {
+        ping.waker.lock().unwrap().replace(cx.waker().clone());
+        if ping.pinged.swap(false, Ordering::AcqRel) { Poll::Ready(()) }
+        else { Poll::Pending }
+      }).await;
+
+      if event_loop_proxy.send_event(PlatformEventExt::AppWake).is_err() { break }
+    }
+  });
+}
+
+
+// adapter to run an AsyncAppHandler as AppHandler
+
+pub struct AsyncApp<A, T: 'static = ()> {
+  ctx: AsyncCtx<T>,
+  placeholder: Option<AppCtx<T>>,
+  future: Option<BoxFuture<'static, ()>>,
+  waker: Waker,
+  app: PhantomData<A>,
+}
+
+impl<A: AsyncAppHandler<T>, T: 'static> AsyncApp<A, T> {
+
+  fn poll(&mut self, app_ctx: &mut AppCtx<T>) {
+
+    let Some(future) = self.future.as_mut() else { return };
+
+    // lend the AppCtx for the duration of the poll
+    let placeholder = self.placeholder.take().unwrap();
+    self.ctx.shared.borrow_mut().app_ctx = Some(mem::replace(app_ctx, placeholder));
+
+    let poll = Pin::as_mut(future).poll(&mut Context::from_waker(&self.waker));
+
+    let lent = self.ctx.shared.borrow_mut().app_ctx.take().expect("AppCtx was returned");
+    self.placeholder = Some(mem::replace(app_ctx, lent));
+
+    if poll.is_ready() {
+      self.future = None;
+      app_ctx.exit = true;
+    }
+  }
+}
+
+impl<A: AsyncAppHandler<T>, T: Clone + 'static> AppHandler<T> for AsyncApp<A, T> {
+
+  type InitData = A::InitData;
+
+  async fn init(app_ctx: &mut AppCtx<T>, init_data: Self::InitData) -> Self {
+
+    let ping = Arc::new(Ping::default());
+
+    if !app_ctx.is_headless() {
+      spawn_pinger(Arc::clone(&ping), app_ctx.event_loop_proxy().clone());
+    }
+
+    let ctx = AsyncCtx { shared: Rc::new(RefCell::new(Shared { events: VecDeque::new(), waiting: None, overflow: false, app_ctx: None })) };
+
+    let mut app = Self {
+      future: Some(Box::pin(A::run(ctx.clone(), init_data))),
+      placeholder: Some(AppCtx::new(None, WindowCtx::headless(WindowId::dummy()))),
+      waker: Waker::from(ping),
+      ctx, app: PhantomData,
+    };
+
+    app.poll(app_ctx);
+    app
+  }
+
+  fn event(&mut self, app_ctx: &mut AppCtx<T>, event: &AppEvent<T>) {
+    self.ctx.shared.borrow_mut().push(event);
+    self.poll(app_ctx);
+  }
+
+  fn wake(&mut self, app_ctx: &mut AppCtx<T>) {
+    self.poll(app_ctx);
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+  use std::{rc::Rc, cell::RefCell};
+  use winit::event::{WindowEvent, DeviceEvent, DeviceId};
+  use crate::{*, time::Duration, headless::HeadlessApp};
+
+  struct Sequence;
+
+  impl AsyncAppHandler for Sequence {
+
+    type InitData = Rc<RefCell<Vec<&'static str>>>;
+
+    async fn run(ctx: AsyncCtx, log: Self::InitData) {
+
+      log.borrow_mut().push("intro");
+
+      while !matches!(ctx.next_event().await, AppEvent::Resumed) {}
+
+      log.borrow_mut().push("loading");
+      sleep(Duration::from_millis(1)).await;
+      log.borrow_mut().push("main");
+
+      loop {
+        if let AppEvent::WindowEvent { event: WindowEvent::RedrawRequested, .. } = ctx.next_event().await {
+          if ctx.with(|app_ctx| app_ctx.window_ctx(app_ctx.main_window_id()).is_some()) {
+            log.borrow_mut().push("redraw");
+            break;
+          }
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn sequential_flow() {
+
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let mut headless = HeadlessApp::<AsyncApp<Sequence>>::new(Rc::clone(&log));
+
+    assert_eq!(*log.borrow(), ["intro"]);
+
+    headless.resumed().resize(800, 600); // queued while loading
+    assert_eq!(*log.borrow(), ["intro", "loading"]);
+
+    std::thread::sleep(Duration::from_millis(1));
+    headless.poll();
+    assert_eq!(*log.borrow(), ["intro", "loading", "main"]);
+    assert!(!headless.exit_requested());
+
+    headless.redraw();
+    assert_eq!(*log.borrow(), ["intro", "loading", "main", "redraw"]);
+    assert!(headless.exit_requested()); // app future finished
+  }
+
+  struct Busy;
+
+  impl AsyncAppHandler for Busy {
+
+    type InitData = Rc<RefCell<Option<AsyncCtx>>>;
+
+    async fn run(ctx: AsyncCtx, slot: Self::InitData) {
+      slot.replace(Some(ctx));
+      std::future::pending::<()>().await;
+    }
+  }
+
+  #[test]
+  fn coalesced_while_busy() {
+
+    let slot = Rc::new(RefCell::new(None));
+    let mut headless = HeadlessApp::<AsyncApp<Busy>>::new(Rc::clone(&slot));
+
+    let motion = |x, y| AppEvent::DeviceEvent { device_id: DeviceId::dummy(), event: DeviceEvent::MouseMotion { delta: (x, y) } };
+    let added = AppEvent::DeviceEvent { device_id: DeviceId::dummy(), event: DeviceEvent::Added };
+
+    // each event is followed by AboutToWait
+    headless.events([motion(1.0, 2.0), motion(3.0, 4.0), added]).resumed();
+
+    let ctx = slot.borrow_mut().take().unwrap();
+    let events: Vec<_> = std::iter::from_fn(|| ctx.try_next_event()).collect();
+
+    assert!(matches!(events.as_slice(), [
+      AppEvent::DeviceEvent { event: DeviceEvent::MouseMotion { delta: (4.0, 6.0) }, .. },
+      AppEvent::AboutToWait,
+      AppEvent::DeviceEvent { event: DeviceEvent::Added, .. },
+      AppEvent::Resumed,
+    ]));
+  }
+}
@@ -0,0 +1,41 @@
+
+use wasm_bindgen::prelude::*;
+use web_sys::BeforeUnloadEvent;
+use js_sys::Function;
+use crate::*;
+
+
+// makes the browser ask before leaving the page, while attached
+
+pub(super) struct BeforeUnloadListener { listener: Function, attached: bool }
+
+impl BeforeUnloadListener {
+
+  pub(super) fn new() -> Self {
+
+    let closure: Box<dyn Fn(BeforeUnloadEvent)> = Box::new(|evt| {
+      evt.prevent_default();
+      evt.set_return_value(""); // legacy browsers
+    });
+
+    Self { listener: Closure::wrap(closure).into_js_value().into(), attached: false }
+  }
+
+  pub(super) fn set_attached(&mut self, attach: bool) {
+
+    if attach == self.attached { return }
+
+    let window = match web_sys::window() {
+      Some(window) => window,
+      None => { log_err!("couldn't get window"); return }
+    };
+
+    let res = if attach { window.add_event_listener_with_callback("beforeunload", &self.listener) }
+    else { window.remove_event_listener_with_callback("beforeunload", &self.listener) };
+
+    match res {
+      Ok(()) => self.attached = attach,
+      Err(_) => log_err!("couldn't update BeforeUnloadListener on window"),
+    }
+  }
+}
@@ -0,0 +1,244 @@
+
+use std::{rc::Rc, cell::RefCell, marker::PhantomData};
+
+#[cfg(not(target_family="wasm"))]
+use {std::cell::Cell, winit::window::WindowId, crate::*};
+
+
+// common clipboard interface, shaped like WebClipboard
+// fetch updates what read returns, for connected clipboards AppEvent::ClipboardFetch follows once done
+// connected native and web clipboards fetch asynchronously, unconnected and in memory ones right away
+
+pub trait Clipboard {
+  fn fetch(&self);
+  fn read(&self) -> Option<String>;
+  fn write(&mut self, text: String);
+  fn is_readable(&self) -> bool;
+  fn is_writeable(&self) -> bool;
+}
+
+
+// one type for every platform, the memory backend stands in for web builds without web_clipboard and web_sys_unstable_apis
+
+#[cfg(not(target_family="wasm"))]
+pub type PlatformClipboard<T = ()> = NativeClipboard<T>;
+
+#[cfg(all(feature = "web_clipboard", target_family="wasm"))]
+#[cfg(web_sys_unstable_apis)]
+pub type PlatformClipboard<T = ()> = super::web_clipboard::WebClipboard<T>;
+
+#[cfg(target_family="wasm")]
+#[cfg(not(all(feature = "web_clipboard", web_sys_unstable_apis)))]
+pub type PlatformClipboard<T = ()> = MemoryClipboard<T>;
+
+
+#[cfg(all(feature = "web_clipboard", target_family="wasm"))]
+#[cfg(web_sys_unstable_apis)]
+impl<T: 'static> Clipboard for super::web_clipboard::WebClipboard<T> {
+  fn fetch(&self) { Self::fetch(self) }
+  fn read(&self) -> Option<String> { Self::read(self) }
+  fn write(&mut self, text: String) { Self::write(self, text) }
+  fn is_readable(&self) -> bool { Self::is_readable(self) }
+  fn is_writeable(&self) -> bool { Self::is_writeable(self) }
+}
+
+
+// system clipboard on native, keeps the last content if the system clipboard isn't available
+// connected ones read it on a background thread, as some systems ask the copying app and may block for a while
+
+#[cfg(not(target_family="wasm"))]
+pub struct NativeClipboard<T: 'static = ()> {
+  content: Rc<RefCell<Option<String>>>,
+  handle: Option<RefCell<arboard::Clipboard>>,
+  event_proxy: Option<(PlatformEventLoopProxy<T>, WindowId)>,
+  generation: Rc<Cell<u64>>, // bumped by fetch and write, so an outdated fetch doesn't overwrite the content
+}
+
+#[cfg(not(target_family="wasm"))]
+impl NativeClipboard {
+
+  pub fn unconnected() -> Self {
+    Self { content: Rc::default(), handle: Self::handle(), event_proxy: None, generation: Rc::default() }
+  }
+}
+
+#[cfg(not(target_family="wasm"))]
+impl<T: 'static> NativeClipboard<T> {
+
+  pub fn connect(app_ctx: &AppCtx<T>) -> Self {
+    Self {
+      content: Rc::default(), handle: Self::handle(),
+      event_proxy: Some((app_ctx.event_loop_proxy().clone(), app_ctx.main_window_id())),
+      generation: Rc::default(),
+    }
+  }
+
+  fn handle() -> Option<RefCell<arboard::Clipboard>> {
+    arboard::Clipboard::new().map(RefCell::new).map_err(|err| log_warn!(err)).ok()
+  }
+
+  pub fn is_connected(&self) -> bool {
+    self.event_proxy.is_some()
+  }
+}
+
+#[cfg(not(target_family="wasm"))]
+impl<T: 'static> Clipboard for NativeClipboard<T> {
+
+  fn fetch(&self) {
+    let Some(handle) = &self.handle else { return };
+
+    let generation = self.generation.get() + 1;
+    self.generation.set(generation);
+
+    let Some((event_loop_proxy, window_id)) = self.event_proxy.clone() else {
+      update_content(&self.content, handle.borrow_mut().get_text());
+      return;
+    };
+
+    // arboard's handle isn't Send on every platform, the thread opens its own
+    let text = in_background(|| arboard::Clipboard::new().and_then(|mut handle| handle.get_text()));
+    let (content, current) = (Rc::clone(&self.content), Rc::clone(&self.generation));
+
+    spawn_local(async move {
+      let text = text.await;
+      if current.get() == generation {
+        update_content(&content, text);
+      }
+      if event_loop_proxy.send_event(PlatformEventExt::ClipboardFetch { window_id }).is_err() {
+        log_err!("couldn't send ClipboardFetch, event loop closed");
+      }
+    });
+  }
+
+  fn read(&self) -> Option<String> {
+    self.content.borrow().clone()
+  }
+
+  fn write(&mut self, text: String) {
+    if let Some(handle) = &self.handle {
+      handle.borrow_mut().set_text(text.as_str()).unwrap_or_else(|err| log_err!(err));
+    }
+    self.generation.set(self.generation.get() + 1);
+    self.content.replace(Some(text));
+  }
+
+  fn is_readable(&self) -> bool { self.handle.is_some() }
+
+  fn is_writeable(&self) -> bool { self.handle.is_some() }
+}
+
+#[cfg(not(target_family="wasm"))]
+fn update_content(content: &RefCell<Option<String>>, text: Result<String, arboard::Error>) {
+  match text {
+    Ok(text) => { content.replace(Some(text)); },
+    Err(arboard::Error::ContentNotAvailable) => { content.replace(None); },
+    Err(err) => log_err!(err),
+  }
+}
+
+#[cfg(not(target_family="wasm"))]
+impl<T: 'static> std::fmt::Debug for NativeClipboard<T> {
+  fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fmt.debug_struct("NativeClipboard")
+    .field("connected", &self.is_connected())
+    .field("readable", &self.is_readable())
+    .field("writeable", &self.is_writeable())
+    .finish()
+  }
+}
+
+
+// for tests, clones share the simulated system clipboard
+// the event type only keeps PlatformClipboard<T> the same type on every platform
+
+pub struct MemoryClipboard<T = ()> {
+  system: Rc<RefCell<Option<String>>>,
+  content: RefCell<Option<String>>,
+  user_event: PhantomData<fn() -> T>,
+}
+
+impl MemoryClipboard {
+
+  pub fn new() -> Self { Self::default() }
+}
+
+impl<T> MemoryClipboard<T> {
+
+  // as if copied by another app, visible after the next fetch
+  pub fn set_system(&self, text: Option<String>) {
+    self.system.replace(text);
+  }
+
+  pub fn system(&self) -> Option<String> {
+    self.system.borrow().clone()
+  }
+}
+
+impl<T> Clipboard for MemoryClipboard<T> {
+
+  fn fetch(&self) {
+    self.content.replace(self.system());
+  }
+
+  fn read(&self) -> Option<String> {
+    self.content.borrow().clone()
+  }
+
+  fn write(&mut self, text: String) {
+    self.system.replace(Some(text.clone()));
+    self.content.replace(Some(text));
+  }
+
+  fn is_readable(&self) -> bool { true }
+
+  fn is_writeable(&self) -> bool { true }
+}
+
+impl<T> Default for MemoryClipboard<T> {
+  fn default() -> Self {
+    Self { system: Rc::default(), content: RefCell::default(), user_event: PhantomData }
+  }
+}
+
+impl<T> Clone for MemoryClipboard<T> {
+  fn clone(&self) -> Self {
+    Self { system: Rc::clone(&self.system), content: self.content.clone(), user_event: PhantomData }
+  }
+}
+
+impl<T> std::fmt::Debug for MemoryClipboard<T> {
+  fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fmt.debug_struct("MemoryClipboard")
+    .field("system", &self.system)
+    .field("content", &self.content)
+    .finish()
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+  use super::{Clipboard, MemoryClipboard};
+
+  fn paste(clipboard: &impl Clipboard) -> Option<String> {
+    clipboard.fetch();
+    clipboard.read()
+  }
+
+  #[test]
+  fn memory_clipboard() {
+
+    let mut clipboard = MemoryClipboard::new();
+    let other = clipboard.clone();
+
+    assert_eq!(paste(&clipboard), None);
+
+    clipboard.write("copied".to_string());
+    assert_eq!(paste(&other), Some("copied".to_string()));
+
+    other.set_system(Some("external".to_string()));
+    assert_eq!(paste(&clipboard), Some("external".to_string()));
+  }
+}
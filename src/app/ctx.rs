@@ -1,46 +1,426 @@
 
-use std::sync::Arc;
-use winit::window::Window;
-use crate::*;
+use std::{sync::Arc, cell::Cell, collections::{HashMap, BTreeMap}, ops::{Deref, DerefMut}};
+use winit::window::{Window, WindowId, WindowAttributes};
+use crate::{*, time::Instant, timer::{Clock, SystemClock}};
+
+#[cfg(feature = "settings")]
+use crate::settings::Settings;
+
+#[cfg(feature = "recorder")]
+use super::{Replay, Recording, ReplayTiming};
+
+#[cfg(feature = "file_dialog")]
+use {std::{rc::Rc, future::Future}, super::{FileDialog, FileDialogResult, FileDialogBackend, PlatformFileDialogs}};
 
 #[cfg(feature = "frame_timer")]
-use crate::{time::Duration, app::STD_DURATION};
+use crate::{time::Duration, timer::FrameStats, app::{STD_DURATION, STD_MAX_UPDATES, FramePacing, ThrottlePolicy}};
+
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct WindowRequest(u64);
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TimerId(u64);
+
+#[cfg(feature = "file_dialog")]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FileDialogId(u64);
 
 
 #[derive(Debug)]
-pub struct AppCtx {
+pub struct WindowCtx {
   #[cfg(feature = "frame_timer")] pub duration: Duration,
   #[cfg(feature = "frame_timer")] pub animate: bool,
   #[cfg(feature = "frame_timer")] pub request: Option<Duration>,
-  #[cfg(feature = "auto_wake_lock")] pub auto_wake_lock: bool,
-  pub exit: bool,
-  event_loop_proxy: PlatformEventLoopProxy,
-  window: Arc<Window>,
+  #[cfg(feature = "frame_timer")] pub pacing: FramePacing,
+  #[cfg(feature = "frame_timer")] pub fixed_update: Option<Duration>,
+  #[cfg(feature = "frame_timer")] pub max_updates: u32,
+  #[cfg(feature = "frame_timer")] pub(super) alpha: f32,
+  #[cfg(feature = "frame_timer")] pub(super) frame_stats: FrameStats,
+  window: Option<Arc<Window>>,
+  window_id: WindowId,
+  redraw_requested: Cell<bool>,
 }
 
 
-impl AppCtx {
+impl WindowCtx {
 
-  pub(super) fn new(event_loop_proxy: PlatformEventLoopProxy, window: Window) -> Self { Self {
+  pub(super) fn new(window: Window) -> Self {
+    Self::with_window(window.id(), Some(Arc::new(window)))
+  }
+
+  // stand-in without a winit window
+  pub(super) fn headless(window_id: WindowId) -> Self {
+    Self::with_window(window_id, None)
+  }
+
+  fn with_window(window_id: WindowId, window: Option<Arc<Window>>) -> Self { Self {
     #[cfg(feature = "frame_timer")] duration: STD_DURATION,
     #[cfg(feature = "frame_timer")] animate: false,
     #[cfg(feature = "frame_timer")] request: None,
-    #[cfg(feature = "auto_wake_lock")] auto_wake_lock: false,
-    exit: false,
-    event_loop_proxy,
-    window: Arc::new(window),
+    #[cfg(feature = "frame_timer")] pacing: FramePacing::Fixed,
+    #[cfg(feature = "frame_timer")] fixed_update: None,
+    #[cfg(feature = "frame_timer")] max_updates: STD_MAX_UPDATES,
+    #[cfg(feature = "frame_timer")] alpha: 1.0,
+    #[cfg(feature = "frame_timer")] frame_stats: FrameStats::default(),
+    window, window_id,
+    redraw_requested: Cell::new(false),
   }}
 
+  pub fn id(&self) -> WindowId {
+    self.window_id
+  }
+
+  pub fn is_headless(&self) -> bool {
+    self.window.is_none()
+  }
+
+  // panics for headless windows
   pub fn window(&self) -> &Window {
-    &self.window
+    self.window.as_deref().expect("headless window has no winit window")
   }
 
   pub fn window_clone(&self) -> Arc<Window> {
-    self.window.clone()
+    self.window.clone().expect("headless window has no winit window")
+  }
+
+  // progress between the last and the next fixed update, to interpolate the drawn state
+  #[cfg(feature = "frame_timer")]
+  pub fn alpha(&self) -> f32 {
+    self.alpha
+  }
+
+  // recorded on redraws while animating, compared to duration
+  #[cfg(feature = "frame_timer")]
+  pub fn frame_stats(&self) -> &FrameStats {
+    &self.frame_stats
+  }
+
+  #[cfg(feature = "frame_timer")]
+  pub fn reset_frame_stats(&mut self) {
+    self.frame_stats.reset();
+  }
+
+  // of the monitor the window is on, None if unknown or headless
+  pub fn refresh_rate_millihertz(&self) -> Option<u32> {
+    self.window.as_ref()?.current_monitor()?.refresh_rate_millihertz()
+  }
+
+  pub fn request_redraw(&self) {
+    match &self.window {
+      Some(window) => window.request_redraw(),
+      None => self.redraw_requested.set(true),
+    }
+  }
+
+  pub(super) fn take_redraw_request(&self) -> bool {
+    self.redraw_requested.take()
+  }
+}
+
+
+#[derive(Debug)]
+pub struct AppCtx<T: 'static = ()> {
+  #[cfg(feature = "auto_wake_lock")] pub auto_wake_lock: bool,
+  #[cfg(feature = "frame_timer")] pub throttle: ThrottlePolicy,
+  #[cfg(feature = "settings")] pub settings: Settings,
+  pub exit: bool,
+  pub unsaved_changes: bool,
+  close_veto: bool,
+  event_loop_proxy: Option<PlatformEventLoopProxy<T>>,
+  main_window_id: WindowId,
+  windows: HashMap<WindowId, WindowCtx>,
+  open_requests: Vec<(WindowRequest, WindowAttributes)>,
+  close_requests: Vec<WindowId>,
+  request_count: u64,
+  timers: BTreeMap<(Instant, TimerId), Option<time::Duration>>,
+  timer_count: u64,
+  clock: Arc<dyn Clock>,
+  #[cfg(feature = "recorder")] replay: Option<Replay<T>>,
+  #[cfg(feature = "file_dialog")] file_dialogs: Rc<dyn FileDialogBackend>,
+  #[cfg(feature = "file_dialog")] dialogs: Vec<(FileDialogId, JoinHandle<FileDialogResult>)>,
+  #[cfg(feature = "file_dialog")] dialog_count: u64,
+}
+
+
+impl<T: 'static> AppCtx<T> {
+
+  pub(super) fn new(event_loop_proxy: Option<PlatformEventLoopProxy<T>>, main_window: WindowCtx) -> Self {
+    Self {
+      #[cfg(feature = "auto_wake_lock")] auto_wake_lock: false,
+      #[cfg(feature = "frame_timer")] throttle: ThrottlePolicy::default(),
+      #[cfg(feature = "settings")] settings: Settings::default(),
+      exit: false,
+      unsaved_changes: false,
+      close_veto: false,
+      event_loop_proxy,
+      main_window_id: main_window.id(),
+      windows: HashMap::from([(main_window.id(), main_window)]),
+      open_requests: Vec::new(),
+      close_requests: Vec::new(),
+      request_count: 0,
+      timers: BTreeMap::new(),
+      timer_count: 0,
+      clock: Arc::new(SystemClock),
+      #[cfg(feature = "recorder")] replay: None,
+      #[cfg(feature = "file_dialog")] file_dialogs: Rc::new(PlatformFileDialogs),
+      #[cfg(feature = "file_dialog")] dialogs: Vec::new(),
+      #[cfg(feature = "file_dialog")] dialog_count: 0,
+    }
+  }
+
+  pub fn is_headless(&self) -> bool {
+    self.event_loop_proxy.is_none()
+  }
+
+  // send PlatformEventExt::User to deliver AppEvent::User, panics for a headless AppCtx
+  pub fn event_loop_proxy(&self) -> &PlatformEventLoopProxy<T> {
+    self.event_loop_proxy.as_ref().expect("headless AppCtx has no event loop proxy")
+  }
+
+
+  // windows
+
+  pub fn main_window_id(&self) -> WindowId {
+    self.main_window_id
+  }
+
+  pub fn window_ctx(&self, window_id: WindowId) -> Option<&WindowCtx> {
+    self.windows.get(&window_id)
+  }
+
+  pub fn window_ctx_mut(&mut self, window_id: WindowId) -> Option<&mut WindowCtx> {
+    self.windows.get_mut(&window_id)
+  }
+
+  pub fn windows(&self) -> impl Iterator<Item=&WindowCtx> {
+    self.windows.values()
+  }
+
+  pub fn windows_mut(&mut self) -> impl Iterator<Item=&mut WindowCtx> {
+    self.windows.values_mut()
+  }
+
+  // window is created after the current event, followed by AppEvent::WindowOpened
+  // on web the canvas isn't mounted automatically, use the WindowAttributesExtWebSys methods
+  pub fn open_window(&mut self, window_attributes: WindowAttributes) -> WindowRequest {
+    self.request_count += 1;
+    let request = WindowRequest(self.request_count);
+    self.open_requests.push((request, window_attributes));
+    request
+  }
+
+  // window is closed after the current event, followed by AppEvent::WindowClosed
+  // closing the main window exits the app
+  pub fn close_window(&mut self, window_id: WindowId) {
+    if window_id == self.main_window_id {
+      self.exit = true;
+    }
+    else if self.windows.contains_key(&window_id) && !self.close_requests.contains(&window_id) {
+      self.close_requests.push(window_id);
+    }
+  }
+
+
+  // time source of the frame timer and the timers
+  pub fn clock(&self) -> &Arc<dyn Clock> {
+    &self.clock
+  }
+
+  pub fn now(&self) -> Instant {
+    self.clock.now()
+  }
+
+  // fires AppEvent::Timer once after the delay
+  pub fn set_timeout(&mut self, delay: time::Duration) -> TimerId {
+    self.insert_timer(delay, None)
   }
 
-  pub fn event_loop_proxy(&self) -> &PlatformEventLoopProxy {
-    &self.event_loop_proxy
+  // fires AppEvent::Timer repeatedly until cancelled, missed intervals are skipped
+  pub fn set_interval(&mut self, interval: time::Duration) -> TimerId {
+    let interval = interval.max(time::Duration::from_millis(1));
+    self.insert_timer(interval, Some(interval))
   }
 
-}
\ No newline at end of file
+  // returns if the timer was pending
+  pub fn cancel_timer(&mut self, timer_id: TimerId) -> bool {
+    let count = self.timers.len();
+    self.timers.retain(|(_, id), _| *id != timer_id);
+    self.timers.len() != count
+  }
+
+
+  // replaces a running replay, events are dispatched like platform events
+  #[cfg(feature = "recorder")]
+  pub fn replay(&mut self, recording: Recording, timing: ReplayTiming) where T: serde::de::DeserializeOwned {
+    self.replay = Some(Replay::new(recording, timing, self.clock.now(), self.main_window_id));
+  }
+
+  #[cfg(feature = "recorder")]
+  pub fn is_replaying(&self) -> bool {
+    self.replay.is_some()
+  }
+
+  #[cfg(feature = "recorder")]
+  pub fn stop_replay(&mut self) {
+    self.replay = None;
+  }
+
+
+  // the dialog is shown right away, followed by AppEvent::FileDialog once closed
+  // on web call them while handling a mouse or key event, browsers need the user activation
+  #[cfg(feature = "file_dialog")]
+  pub fn open_file(&mut self, dialog: FileDialog) -> FileDialogId {
+    let future = self.file_dialogs.open(&dialog, false);
+    self.spawn_dialog(async move { future.await.map(FileDialogResult::Opened) })
+  }
+
+  #[cfg(feature = "file_dialog")]
+  pub fn open_files(&mut self, dialog: FileDialog) -> FileDialogId {
+    let future = self.file_dialogs.open(&dialog, true);
+    self.spawn_dialog(async move { future.await.map(FileDialogResult::Opened) })
+  }
+
+  #[cfg(feature = "file_dialog")]
+  pub fn save_file(&mut self, dialog: FileDialog, data: Vec<u8>) -> FileDialogId {
+    let future = self.file_dialogs.save(&dialog, data);
+    self.spawn_dialog(async move { future.await.map(FileDialogResult::Saved) })
+  }
+
+  // PlatformFileDialogs by default, ScriptedFileDialogs for tests
+  #[cfg(feature = "file_dialog")]
+  pub fn set_file_dialog_backend(&mut self, backend: impl FileDialogBackend + 'static) {
+    self.file_dialogs = Rc::new(backend);
+  }
+
+
+  // keeps the window open when called on WindowEvent::CloseRequested
+  // with unsaved_changes closing the main window is vetoed by default, confirm it with close_window
+  // other windows close unless vetoed here
+  // on web unsaved_changes makes the browser ask before leaving the page
+  pub fn veto_close(&mut self) {
+    self.close_veto = true;
+  }
+
+
+  // internal
+
+  #[cfg(feature = "recorder")]
+  pub(super) fn replay_mut(&mut self) -> Option<&mut Replay<T>> {
+    self.replay.as_mut()
+  }
+
+  #[cfg(feature = "recorder")]
+  pub(super) fn replay_deadline(&self) -> Option<Instant> {
+    self.replay.as_ref().and_then(Replay::deadline)
+  }
+
+  #[cfg(feature = "recorder")]
+  pub(super) fn pop_replay_event(&mut self, now: Instant) -> Option<AppEvent<T>> {
+    let replay = self.replay.as_mut()?;
+    let event = replay.pop_due(now);
+    if replay.is_finished() { self.replay = None }
+    event
+  }
+
+  // the app is woken when the dialog closes, AppState then delivers the result
+  #[cfg(feature = "file_dialog")]
+  fn spawn_dialog(&mut self, future: impl Future<Output=anyhow::Result<FileDialogResult>> + 'static) -> FileDialogId {
+
+    self.dialog_count += 1;
+    let dialog_id = FileDialogId(self.dialog_count);
+    let event_loop_proxy = self.event_loop_proxy.clone();
+
+    let handle = spawn_local(async move {
+      let result = future.await.unwrap_or_else(|err| FileDialogResult::Failed(err.to_string()));
+      if let Some(event_loop_proxy) = event_loop_proxy {
+        if event_loop_proxy.send_event(PlatformEventExt::AppWake).is_err() {
+          log_err!("couldn't send AppWake, event loop closed");
+        }
+      }
+      result
+    });
+
+    self.dialogs.push((dialog_id, handle));
+    dialog_id
+  }
+
+  #[cfg(feature = "file_dialog")]
+  pub(super) fn pop_closed_dialog(&mut self) -> Option<(FileDialogId, FileDialogResult)> {
+    let index = self.dialogs.iter().position(|(_, handle)| handle.is_finished())?;
+    let (dialog_id, mut handle) = self.dialogs.remove(index);
+    handle.try_take().map(|result| (dialog_id, result))
+  }
+
+  pub(super) fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+    self.clock = clock;
+  }
+
+  fn insert_timer(&mut self, delay: time::Duration, interval: Option<time::Duration>) -> TimerId {
+    self.timer_count += 1;
+    let timer_id = TimerId(self.timer_count);
+    self.timers.insert((self.clock.now() + delay, timer_id), interval);
+    timer_id
+  }
+
+  pub(super) fn next_timer(&self) -> Option<Instant> {
+    self.timers.first_key_value().map(|((deadline, _), _)| *deadline)
+  }
+
+  // one at a time, so timers cancelled by the handler don't fire
+  pub(super) fn pop_due_timer(&mut self, now: Instant) -> Option<TimerId> {
+
+    let entry = self.timers.first_entry().filter(|entry| entry.key().0 <= now)?;
+    let ((deadline, timer_id), interval) = entry.remove_entry();
+
+    if let Some(interval) = interval {
+      let next = deadline + interval;
+      self.timers.insert((if next > now { next } else { now + interval }, timer_id), Some(interval));
+    }
+
+    Some(timer_id)
+  }
+
+  pub(super) fn set_close_veto(&mut self, veto: bool) {
+    self.close_veto = veto;
+  }
+
+  pub(super) fn take_close_veto(&mut self) -> bool {
+    std::mem::take(&mut self.close_veto)
+  }
+
+  pub(super) fn take_open_requests(&mut self) -> Vec<(WindowRequest, WindowAttributes)> {
+    std::mem::take(&mut self.open_requests)
+  }
+
+  pub(super) fn take_close_requests(&mut self) -> Vec<WindowId> {
+    std::mem::take(&mut self.close_requests)
+  }
+
+  pub(super) fn insert_window(&mut self, window_ctx: WindowCtx) -> WindowId {
+    let window_id = window_ctx.id();
+    self.windows.insert(window_id, window_ctx);
+    window_id
+  }
+
+  pub(super) fn remove_window(&mut self, window_id: WindowId) -> Option<WindowCtx> {
+    if window_id == self.main_window_id { None }
+    else { self.windows.remove(&window_id) }
+  }
+}
+
+
+// main window access
+
+impl<T: 'static> Deref for AppCtx<T> {
+  type Target = WindowCtx;
+  fn deref(&self) -> &WindowCtx {
+    self.windows.get(&self.main_window_id).expect("main window is present")
+  }
+}
+
+impl<T: 'static> DerefMut for AppCtx<T> {
+  fn deref_mut(&mut self) -> &mut WindowCtx {
+    self.windows.get_mut(&self.main_window_id).expect("main window is present")
+  }
+}
@@ -0,0 +1,373 @@
+
+use std::{future::Future, pin::Pin, rc::Rc, cell::RefCell, collections::VecDeque, fmt::Debug};
+use anyhow::Result as Res;
+use super::DroppedFile;
+
+#[cfg(not(target_family="wasm"))]
+use {std::{path::PathBuf, fs}, anyhow::Context, crate::in_background};
+
+#[cfg(target_family="wasm")]
+use {
+  web_sys::{HtmlInputElement, HtmlAnchorElement, Blob, Url, AddEventListenerOptions},
+  js_sys::{Array, Uint8Array, Promise, Function, Reflect},
+  wasm_bindgen::{JsValue, JsCast, closure::Closure},
+  wasm_bindgen_futures::JsFuture,
+  anyhow::{Context, anyhow},
+  crate::{spawn_local, sleep, time::Duration},
+};
+
+
+// what the dialog shows, unsupported options are ignored by the backend
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FileDialog {
+  pub title: Option<String>,
+  pub filters: Vec<FileFilter>,
+  pub file_name: Option<String>, // suggested name when saving
+  #[cfg(not(target_family="wasm"))] pub directory: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileFilter {
+  pub name: String,
+  pub extensions: Vec<String>, // without the dot
+}
+
+impl FileDialog {
+
+  pub fn new() -> Self { Self::default() }
+
+  pub fn with_title(mut self, title: impl Into<String>) -> Self {
+    self.title = Some(title.into());
+    self
+  }
+
+  pub fn with_filter(mut self, name: impl Into<String>, extensions: &[&str]) -> Self {
+    self.filters.push(FileFilter { name: name.into(), extensions: extensions.iter().map(|ext| ext.to_string()).collect() });
+    self
+  }
+
+  pub fn with_file_name(mut self, file_name: impl Into<String>) -> Self {
+    self.file_name = Some(file_name.into());
+    self
+  }
+
+  #[cfg(not(target_family="wasm"))]
+  pub fn with_directory(mut self, directory: impl Into<PathBuf>) -> Self {
+    self.directory = Some(directory.into());
+    self
+  }
+
+  // awaitable with the platform backend, for code running under spawn_local
+  // AppCtx::open_file and save_file deliver the result as AppEvent::FileDialog instead
+
+  pub fn open_file(&self) -> impl Future<Output=Res<Option<DroppedFile>>> + 'static {
+    let future = PlatformFileDialogs.open(self, false);
+    async move { Ok(future.await?.into_iter().next()) }
+  }
+
+  pub fn open_files(&self) -> impl Future<Output=Res<Vec<DroppedFile>>> + 'static {
+    PlatformFileDialogs.open(self, true)
+  }
+
+  pub fn save_file(&self, data: Vec<u8>) -> impl Future<Output=Res<Option<String>>> + 'static {
+    PlatformFileDialogs.save(self, data)
+  }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileDialogResult {
+  Opened(Vec<DroppedFile>), // empty if cancelled
+  Saved(Option<String>), // the chosen name, a path on native, None if cancelled, on web always the suggested name
+  Failed(String),
+}
+
+
+// the dialog is shown when open or save is called, the future resolves once it's closed
+
+pub trait FileDialogBackend: Debug {
+  fn open(&self, dialog: &FileDialog, multiple: bool) -> Pin<Box<dyn Future<Output=Res<Vec<DroppedFile>>>>>;
+  fn save(&self, dialog: &FileDialog, data: Vec<u8>) -> Pin<Box<dyn Future<Output=Res<Option<String>>>>>;
+}
+
+
+// native dialogs through rfd, the xdg desktop portal on linux
+// web: a hidden <input type=file> to open and a download to save, both need a user activation
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PlatformFileDialogs;
+
+#[cfg(not(target_family="wasm"))]
+impl PlatformFileDialogs {
+  fn rfd_dialog(dialog: &FileDialog) -> rfd::AsyncFileDialog {
+
+    let mut rfd_dialog = rfd::AsyncFileDialog::new();
+
+    if let Some(title) = &dialog.title { rfd_dialog = rfd_dialog.set_title(title) }
+    if let Some(file_name) = &dialog.file_name { rfd_dialog = rfd_dialog.set_file_name(file_name) }
+    if let Some(directory) = &dialog.directory { rfd_dialog = rfd_dialog.set_directory(directory) }
+
+    for filter in &dialog.filters {
+      rfd_dialog = rfd_dialog.add_filter(&filter.name, &filter.extensions);
+    }
+
+    rfd_dialog
+  }
+}
+
+#[cfg(not(target_family="wasm"))]
+impl FileDialogBackend for PlatformFileDialogs {
+
+  fn open(&self, dialog: &FileDialog, multiple: bool) -> Pin<Box<dyn Future<Output=Res<Vec<DroppedFile>>>>> {
+    let rfd_dialog = Self::rfd_dialog(dialog);
+    Box::pin(async move {
+      let handles = match multiple {
+        true => rfd_dialog.pick_files().await.unwrap_or_default(),
+        false => rfd_dialog.pick_file().await.into_iter().collect(),
+      };
+      Ok(handles.into_iter().map(|handle| DroppedFile::from_path(handle.path().to_path_buf())).collect())
+    })
+  }
+
+  fn save(&self, dialog: &FileDialog, data: Vec<u8>) -> Pin<Box<dyn Future<Output=Res<Option<String>>>>> {
+    let rfd_dialog = Self::rfd_dialog(dialog);
+    Box::pin(async move {
+      let Some(handle) = rfd_dialog.save_file().await else { return Ok(None) };
+      let path = handle.path().to_path_buf();
+      in_background(move || {
+        fs::write(&path, data).with_context(|| format!("couldn't write {}", path.display()))?;
+        Ok(Some(path.display().to_string()))
+      }).await
+    })
+  }
+}
+
+// without a cancel event, the window gets focus back when the picker closes, a change may still follow shortly
+#[cfg(target_family="wasm")]
+const CANCEL_DELAY: Duration = Duration::from_secs(1);
+
+// resolves an open on the next window focus, detached when dropped
+
+#[cfg(target_family="wasm")]
+struct FocusFallback {
+  window: web_sys::Window,
+  listener: Closure<dyn FnMut()>,
+}
+
+#[cfg(target_family="wasm")]
+impl FocusFallback {
+
+  fn attach(resolve: Function) -> Option<Self> {
+
+    let window = web_sys::window()?;
+
+    let listener = Closure::once(move || {
+      spawn_local(async move {
+        sleep(CANCEL_DELAY).await;
+        let _ = resolve.call0(&JsValue::NULL); // ignored if already resolved
+      });
+    });
+
+    let options = AddEventListenerOptions::new();
+    options.set_once(true);
+    window.add_event_listener_with_callback_and_add_event_listener_options("focus", listener.as_ref().unchecked_ref(), &options).ok()?;
+
+    Some(Self { window, listener })
+  }
+}
+
+#[cfg(target_family="wasm")]
+impl Drop for FocusFallback {
+  fn drop(&mut self) {
+    let _ = self.window.remove_event_listener_with_callback("focus", self.listener.as_ref().unchecked_ref());
+  }
+}
+
+// removing the link and revoking its url right after the click can abort the download in some browsers
+#[cfg(target_family="wasm")]
+const DOWNLOAD_CLEANUP_DELAY: Duration = Duration::from_secs(40);
+
+#[cfg(target_family="wasm")]
+impl FileDialogBackend for PlatformFileDialogs {
+
+  fn open(&self, dialog: &FileDialog, multiple: bool) -> Pin<Box<dyn Future<Output=Res<Vec<DroppedFile>>>>> {
+
+    let input = (|| -> Result<HtmlInputElement, JsValue> {
+      let document = web_sys::window().and_then(|win| win.document()).ok_or("couldn't get window.document")?;
+      let input: HtmlInputElement = document.create_element("input")?.dyn_into()?;
+      input.set_type("file");
+      input.set_multiple(multiple);
+
+      let accept: Vec<String> = dialog.filters.iter().flat_map(|filter| &filter.extensions).map(|ext| format!(".{ext}")).collect();
+      input.set_accept(&accept.join(","));
+      Ok(input)
+    })();
+
+    // resolves on change, or on cancel where supported, otherwise a while after the window is focused again
+    let mut fallback = None;
+    let closed = input.as_ref().map_err(Clone::clone).map(|input| Promise::new(&mut |resolve, _reject| {
+      input.set_onchange(Some(&resolve));
+      input.set_oncancel(Some(&resolve));
+      if !Reflect::has(input, &JsValue::from_str("oncancel")).unwrap_or(false) {
+        fallback = FocusFallback::attach(resolve);
+      }
+    }));
+
+    if let Ok(input) = &input { input.click() }
+
+    Box::pin(async move {
+      let input = input.map_err(|err| anyhow!("{err:?}"))?;
+      JsFuture::from(closed.map_err(|err| anyhow!("{err:?}"))?).await.map_err(|err| anyhow!("{err:?}"))?;
+      drop(fallback);
+      let files = input.files().context("no files on the input")?;
+      Ok((0..files.length()).filter_map(|i| files.get(i)).map(DroppedFile::from_file).collect())
+    })
+  }
+
+  // the browser saves to the downloads, the result is the suggested name once the download starts
+  // it can't tell if the user renamed or cancelled it
+  fn save(&self, dialog: &FileDialog, data: Vec<u8>) -> Pin<Box<dyn Future<Output=Res<Option<String>>>>> {
+
+    let file_name = dialog.file_name.clone().unwrap_or_else(|| "download".to_string());
+
+    let res = (|| -> Result<(), JsValue> {
+      let document = web_sys::window().and_then(|win| win.document()).ok_or("couldn't get window.document")?;
+      let body = document.body().ok_or("couldn't get document.body")?;
+      let blob = Blob::new_with_u8_array_sequence(&Array::of1(&Uint8Array::from(data.as_slice())))?;
+      let url = Url::create_object_url_with_blob(&blob)?;
+
+      let anchor: HtmlAnchorElement = document.create_element("a")?.dyn_into()?;
+      anchor.set_href(&url);
+      anchor.set_download(&file_name);
+
+      // some browsers only follow links in the document
+      body.append_child(&anchor)?;
+      anchor.click();
+
+      spawn_local(async move {
+        sleep(DOWNLOAD_CLEANUP_DELAY).await;
+        anchor.remove();
+        let _ = Url::revoke_object_url(&url);
+      });
+
+      Ok(())
+    })();
+
+    Box::pin(async move {
+      res.map_err(|err| anyhow!("{err:?}"))?;
+      Ok(Some(file_name))
+    })
+  }
+}
+
+
+// for tests, clones share the script
+// opens return the pushed files in order, or are cancelled, saves keep the data
+
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedFileDialogs {
+  script: Rc<RefCell<Script>>,
+}
+
+#[derive(Debug, Default)]
+struct Script {
+  opens: VecDeque<Vec<DroppedFile>>,
+  saves: VecDeque<Option<String>>,
+  shown: Vec<FileDialog>,
+  saved: Vec<(String, Vec<u8>)>,
+}
+
+impl ScriptedFileDialogs {
+
+  pub fn new() -> Self { Self::default() }
+
+  // files for the next open, empty to cancel
+  pub fn push_open(&self, files: Vec<DroppedFile>) {
+    self.script.borrow_mut().opens.push_back(files);
+  }
+
+  // name for the next save, None to cancel, defaults to FileDialog::file_name
+  pub fn push_save(&self, file_name: Option<String>) {
+    self.script.borrow_mut().saves.push_back(file_name);
+  }
+
+  pub fn shown(&self) -> Vec<FileDialog> {
+    self.script.borrow().shown.clone()
+  }
+
+  pub fn saved(&self) -> Vec<(String, Vec<u8>)> {
+    self.script.borrow().saved.clone()
+  }
+}
+
+impl FileDialogBackend for ScriptedFileDialogs {
+
+  fn open(&self, dialog: &FileDialog, multiple: bool) -> Pin<Box<dyn Future<Output=Res<Vec<DroppedFile>>>>> {
+    let mut script = self.script.borrow_mut();
+    script.shown.push(dialog.clone());
+    let mut files = script.opens.pop_front().unwrap_or_default();
+    if !multiple { files.truncate(1) }
+    Box::pin(std::future::ready(Ok(files)))
+  }
+
+  fn save(&self, dialog: &FileDialog, data: Vec<u8>) -> Pin<Box<dyn Future<Output=Res<Option<String>>>>> {
+    let mut script = self.script.borrow_mut();
+    script.shown.push(dialog.clone());
+    let file_name = script.saves.pop_front().unwrap_or_else(|| dialog.file_name.clone());
+    if let Some(file_name) = &file_name {
+      script.saved.push((file_name.clone(), data));
+    }
+    Box::pin(std::future::ready(Ok(file_name)))
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+  use crate::{AppHandler, AppCtx, AppEvent, DroppedFile, headless::HeadlessApp};
+  use super::{FileDialog, FileDialogResult, ScriptedFileDialogs};
+
+  #[derive(Default)]
+  struct Editor { results: Vec<FileDialogResult> }
+
+  impl AppHandler for Editor {
+
+    type InitData = ();
+
+    async fn init(_app_ctx: &mut AppCtx, _init_data: ()) -> Self { Self::default() }
+
+    fn event(&mut self, _app_ctx: &mut AppCtx, event: &AppEvent) {
+      if let AppEvent::FileDialog { result, .. } = event {
+        self.results.push(result.clone());
+      }
+    }
+  }
+
+  #[test]
+  fn scripted_dialogs() {
+
+    let dialogs = ScriptedFileDialogs::new();
+    let file = DroppedFile::from_bytes("notes.txt", "text/plain", b"notes".as_slice());
+    dialogs.push_open(vec![file.clone()]);
+    dialogs.push_save(None);
+
+    let mut headless = HeadlessApp::<Editor>::new(());
+    headless.app_ctx_mut().set_file_dialog_backend(dialogs.clone());
+
+    let open = FileDialog::new().with_title("Open").with_filter("Text", &["txt"]);
+    headless.app_ctx_mut().open_file(open.clone());
+    headless.app_ctx_mut().save_file(FileDialog::new(), b"cancelled".to_vec());
+    headless.app_ctx_mut().save_file(FileDialog::new().with_file_name("out.txt"), b"saved".to_vec());
+    headless.poll();
+
+    assert_eq!(headless.app().results, [
+      FileDialogResult::Opened(vec![file]),
+      FileDialogResult::Saved(None),
+      FileDialogResult::Saved(Some("out.txt".to_string())),
+    ]);
+    assert_eq!(dialogs.shown()[0], open);
+    assert_eq!(dialogs.saved(), [("out.txt".to_string(), b"saved".to_vec())]);
+  }
+}
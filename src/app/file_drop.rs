@@ -0,0 +1,376 @@
+
+use std::{future::Future, sync::Arc};
+use winit::{window::WindowId, dpi::PhysicalPosition};
+use anyhow::Result as Res;
+use crate::*;
+use super::AppCtx;
+
+#[cfg(not(target_family="wasm"))]
+use {
+  std::{path::{Path, PathBuf}, fs, collections::{HashMap, HashSet}},
+  winit::event::WindowEvent,
+  anyhow::Context,
+  super::AppEvent,
+};
+
+#[cfg(target_family="wasm")]
+use {
+  std::collections::HashMap,
+  web_sys::{DragEvent, File, HtmlCanvasElement},
+  js_sys::{Function, Uint8Array},
+  wasm_bindgen::{JsValue, closure::Closure},
+  wasm_bindgen_futures::JsFuture,
+  winit::platform::web::WindowExtWebSys,
+  anyhow::anyhow,
+};
+
+
+// file dropped on a window or picked in a file dialog
+// a path on native and a DOM File on web, or contents in memory
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DroppedFile {
+  pub name: String,
+  pub mime: String, // guessed from the extension on native, empty if unknown
+  pub size: u64,
+  source: Source,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Source {
+  #[cfg(not(target_family="wasm"))] Path(PathBuf),
+  #[cfg(target_family="wasm")] File(File),
+  Memory(Arc<[u8]>),
+}
+
+impl DroppedFile {
+
+  #[cfg(not(target_family="wasm"))]
+  pub fn from_path(path: PathBuf) -> Self {
+    Self {
+      name: path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default(),
+      mime: path.extension().and_then(|ext| ext.to_str()).map(mime_from_extension).unwrap_or_default().to_string(),
+      size: fs::metadata(&path).map(|metadata| metadata.len()).unwrap_or(0),
+      source: Source::Path(path),
+    }
+  }
+
+  #[cfg(target_family="wasm")]
+  pub fn from_file(file: File) -> Self {
+    Self { name: file.name(), mime: file.type_(), size: file.size() as u64, source: Source::File(file) }
+  }
+
+  pub fn from_bytes(name: impl Into<String>, mime: impl Into<String>, data: impl Into<Arc<[u8]>>) -> Self {
+    let data = data.into();
+    Self { name: name.into(), mime: mime.into(), size: data.len() as u64, source: Source::Memory(data) }
+  }
+
+  #[cfg(not(target_family="wasm"))]
+  pub fn path(&self) -> Option<&Path> {
+    match &self.source { Source::Path(path) => Some(path), _ => None }
+  }
+
+  #[cfg(target_family="wasm")]
+  pub fn file(&self) -> Option<&File> {
+    match &self.source { Source::File(file) => Some(file), _ => None }
+  }
+
+  // read on a background thread on native, the future doesn't borrow the file
+  pub fn bytes(&self) -> impl Future<Output=Res<Vec<u8>>> + 'static {
+
+    let source = self.source.clone();
+
+    async move {
+      match source {
+
+        #[cfg(not(target_family="wasm"))]
+        Source::Path(path) => in_background(move || {
+          fs::read(&path).with_context(|| format!("couldn't read {}", path.display()))
+        }).await,
+
+        #[cfg(target_family="wasm")]
+        Source::File(file) => {
+          let buffer = JsFuture::from(file.array_buffer()).await.map_err(|err| anyhow!("{err:?}"))?;
+          Ok(Uint8Array::new(&buffer).to_vec())
+        },
+
+        Source::Memory(data) => Ok(data.to_vec()),
+      }
+    }
+  }
+}
+
+#[cfg(not(target_family="wasm"))]
+fn mime_from_extension(ext: &str) -> &'static str {
+  match ext.to_ascii_lowercase().as_str() {
+    "txt" => "text/plain",
+    "html" | "htm" => "text/html",
+    "css" => "text/css",
+    "csv" => "text/csv",
+    "json" => "application/json",
+    "pdf" => "application/pdf",
+    "zip" => "application/zip",
+    "png" => "image/png",
+    "jpg" | "jpeg" => "image/jpeg",
+    "gif" => "image/gif",
+    "webp" => "image/webp",
+    "svg" => "image/svg+xml",
+    "wav" => "audio/wav",
+    "mp3" => "audio/mpeg",
+    "ogg" => "audio/ogg",
+    "mp4" => "video/mp4",
+    _ => "",
+  }
+}
+
+
+// native: winit sends HoveredFile and DroppedFile per file, they're combined into one hover and one drop
+// the position is the last cursor position, winit doesn't report one for drops
+// web: drag listeners on the canvas send the events through the event loop proxy
+
+#[derive(Default)]
+pub(super) struct FileDropTracker {
+  #[cfg(not(target_family="wasm"))] hovered: HashSet<WindowId>,
+  #[cfg(not(target_family="wasm"))] dropped: Vec<(WindowId, Vec<DroppedFile>)>,
+  #[cfg(not(target_family="wasm"))] cursors: HashMap<WindowId, PhysicalPosition<f64>>,
+  #[cfg(not(target_family="wasm"))] events: Vec<(WindowId, FileDropEvent)>,
+  #[cfg(target_family="wasm")] targets: HashMap<WindowId, DropTarget>,
+}
+
+#[cfg(not(target_family="wasm"))]
+enum FileDropEvent { HoverEntered, HoverLeft, Dropped(Vec<DroppedFile>) }
+
+impl FileDropTracker {
+
+  pub(super) fn new<T: 'static>(app_ctx: &AppCtx<T>) -> Self {
+    let mut tracker = Self::default();
+    for window_ctx in app_ctx.windows() {
+      tracker.window_opened(app_ctx, window_ctx.id());
+    }
+    tracker
+  }
+
+  #[cfg_attr(not(target_family="wasm"), allow(unused_variables))]
+  pub(super) fn window_opened<T: 'static>(&mut self, app_ctx: &AppCtx<T>, window_id: WindowId) {
+    #[cfg(target_family="wasm")]
+    if let Some(window_ctx) = app_ctx.window_ctx(window_id).filter(|window_ctx| !window_ctx.is_headless()) {
+      if let Some(target) = DropTarget::attach(window_ctx.window(), window_id, app_ctx.event_loop_proxy().clone()) {
+        self.targets.insert(window_id, target);
+      }
+    }
+  }
+
+  pub(super) fn window_closed(&mut self, window_id: WindowId) {
+    #[cfg(not(target_family="wasm"))] {
+      self.hovered.remove(&window_id);
+      self.cursors.remove(&window_id);
+      self.dropped.retain(|(id, _)| *id != window_id);
+      self.events.retain(|(id, _)| *id != window_id);
+    }
+    #[cfg(target_family="wasm")] {
+      self.targets.remove(&window_id);
+    }
+  }
+
+  #[cfg(not(target_family="wasm"))]
+  pub(super) fn window_event(&mut self, window_id: WindowId, event: &WindowEvent) {
+    match event {
+
+      WindowEvent::CursorMoved { position, .. } => { self.cursors.insert(window_id, *position); },
+
+      WindowEvent::CursorLeft { .. } => { self.cursors.remove(&window_id); },
+
+      WindowEvent::HoveredFile(_) if self.hovered.insert(window_id) => {
+        self.events.push((window_id, FileDropEvent::HoverEntered));
+      },
+
+      WindowEvent::HoveredFileCancelled if self.hovered.remove(&window_id) => {
+        self.events.push((window_id, FileDropEvent::HoverLeft));
+      },
+
+      WindowEvent::DroppedFile(path) => {
+        self.hovered.remove(&window_id);
+        match self.dropped.iter_mut().find(|(id, _)| *id == window_id) {
+          Some((_, files)) => files.push(DroppedFile::from_path(path.clone())),
+          None => self.dropped.push((window_id, vec![DroppedFile::from_path(path.clone())])),
+        }
+      },
+
+      _ => {},
+    }
+  }
+
+  // all files of a drop arrive before AboutToWait
+  #[cfg(not(target_family="wasm"))]
+  pub(super) fn take_events<T>(&mut self) -> Vec<AppEvent<T>> {
+
+    for (window_id, files) in self.dropped.drain(..) {
+      self.events.push((window_id, FileDropEvent::Dropped(files)));
+    }
+
+    self.events.drain(..).map(|(window_id, event)| {
+      let position = self.cursors.get(&window_id).copied();
+      match event {
+        FileDropEvent::HoverEntered => AppEvent::FileHoverEntered { window_id, position },
+        FileDropEvent::HoverLeft => AppEvent::FileHoverLeft { window_id },
+        FileDropEvent::Dropped(files) => AppEvent::FilesDropped { window_id, files, position },
+      }
+    }).collect()
+  }
+}
+
+
+#[cfg(target_family="wasm")]
+struct DropTarget {
+  canvas: HtmlCanvasElement,
+  listeners: Vec<(&'static str, Function)>,
+}
+
+#[cfg(target_family="wasm")]
+impl DropTarget {
+
+  fn attach<T: 'static>(window: &winit::window::Window, window_id: WindowId, event_loop_proxy: PlatformEventLoopProxy<T>) -> Option<Self> {
+
+    let canvas = window.canvas()?;
+
+    fn has_files(evt: &DragEvent) -> bool {
+      evt.data_transfer().is_some_and(|transfer| transfer.types().includes(&JsValue::from("Files"), 0))
+    }
+
+    // offset is in css pixels, a float with web_sys_unstable_apis
+    fn position(evt: &DragEvent) -> Option<PhysicalPosition<f64>> {
+      let scale = web_sys::window()?.device_pixel_ratio();
+      // the offsets are f64 with web_sys_unstable_apis and i32 without
+      #[cfg(web_sys_unstable_apis)] let (x, y) = (evt.offset_x(), evt.offset_y());
+      #[cfg(not(web_sys_unstable_apis))] let (x, y) = (f64::from(evt.offset_x()), f64::from(evt.offset_y()));
+      Some(PhysicalPosition::new(x * scale, y * scale))
+    }
+
+    let send = move |event: PlatformEventExt<T>| {
+      if event_loop_proxy.send_event(event).is_err() {
+        log_err!("couldn't send file drop event, event loop closed");
+      }
+    };
+
+    let listener = |func: Box<dyn Fn(DragEvent)>| -> Function { Closure::wrap(func).into_js_value().into() };
+
+    let listeners = vec![
+      ("dragenter", listener(Box::new({
+        let send = send.clone();
+        move |evt| if has_files(&evt) {
+          evt.prevent_default();
+          send(PlatformEventExt::FileHoverEntered { window_id, position: position(&evt) });
+        }
+      }))),
+      // needed to allow the drop
+      ("dragover", listener(Box::new(|evt| if has_files(&evt) {
+        evt.prevent_default();
+        if let Some(transfer) = evt.data_transfer() { transfer.set_drop_effect("copy") }
+      }))),
+      ("dragleave", listener(Box::new({
+        let send = send.clone();
+        move |evt| if has_files(&evt) {
+          send(PlatformEventExt::FileHoverLeft { window_id });
+        }
+      }))),
+      // keeps the browser from opening the files
+      ("drop", listener(Box::new(move |evt| {
+        let Some(files) = evt.data_transfer().and_then(|transfer| transfer.files()) else { return };
+        evt.prevent_default();
+        let files = (0..files.length()).filter_map(|i| files.get(i)).map(DroppedFile::from_file).collect();
+        send(PlatformEventExt::FilesDropped { window_id, files, position: position(&evt) });
+      }))),
+    ];
+
+    for (event_type, listener) in &listeners {
+      if canvas.add_event_listener_with_callback(event_type, listener).is_err() {
+        log_err!("couldn't attach drop listeners to the canvas");
+        return None;
+      }
+    }
+
+    Some(Self { canvas, listeners })
+  }
+}
+
+#[cfg(target_family="wasm")]
+impl Drop for DropTarget {
+  fn drop(&mut self) {
+    for (event_type, listener) in &self.listeners {
+      let _ = self.canvas.remove_event_listener_with_callback(event_type, listener);
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+  use std::path::PathBuf;
+  use winit::event::WindowEvent;
+  use crate::{AppHandler, AppCtx, AppEvent, headless::HeadlessApp};
+
+  #[derive(Default)]
+  struct DropTarget { hovering: bool, dropped: Vec<String>, drops: usize }
+
+  impl AppHandler for DropTarget {
+
+    type InitData = ();
+
+    async fn init(_app_ctx: &mut AppCtx, _init_data: ()) -> Self { Self::default() }
+
+    fn event(&mut self, _app_ctx: &mut AppCtx, event: &AppEvent) {
+      match event {
+        AppEvent::FileHoverEntered {..} => self.hovering = true,
+        AppEvent::FileHoverLeft {..} => self.hovering = false,
+        AppEvent::FilesDropped { files, .. } => {
+          self.hovering = false;
+          self.drops += 1;
+          self.dropped.extend(files.iter().map(|file| format!("{} {}", file.name, file.mime)));
+        },
+        _ => {},
+      }
+    }
+  }
+
+  #[test]
+  fn dropped_files() {
+
+    let mut headless = HeadlessApp::<DropTarget>::new(());
+    let window_id = headless.main_window_id();
+
+    headless.window_event(window_id, WindowEvent::HoveredFile(PathBuf::from("a.png")));
+    assert!(headless.app().hovering);
+
+    headless.window_event(window_id, WindowEvent::HoveredFileCancelled);
+    assert!(!headless.app().hovering);
+
+    headless.events([
+      AppEvent::WindowEvent { window_id, event: WindowEvent::DroppedFile(PathBuf::from("dir/a.png")) },
+      AppEvent::WindowEvent { window_id, event: WindowEvent::DroppedFile(PathBuf::from("b.unknown")) },
+    ]);
+    assert_eq!(headless.app().dropped, ["a.png image/png", "b.unknown "]);
+
+    let path = std::env::temp_dir().join(format!("platform-drop-{}.txt", std::process::id()));
+    std::fs::write(&path, "dropped").unwrap();
+    let file = super::DroppedFile::from_path(path.clone());
+    assert_eq!(file.size, 7);
+    assert_eq!(pollster::block_on(file.bytes()).unwrap(), b"dropped");
+    std::fs::remove_file(&path).unwrap();
+
+    let file = super::DroppedFile::from_bytes("memory.txt", "text/plain", b"in memory".as_slice());
+    assert_eq!(pollster::block_on(file.bytes()).unwrap(), b"in memory");
+  }
+
+  #[test]
+  fn multi_file_drop() {
+
+    let mut headless = HeadlessApp::<DropTarget>::new(());
+    let window_id = headless.main_window_id();
+    let dropped = |name: &str| AppEvent::WindowEvent { window_id, event: WindowEvent::DroppedFile(PathBuf::from(name)) };
+
+    headless.batch([dropped("a.txt"), dropped("b.txt"), dropped("c.txt")]);
+    assert_eq!(headless.app().drops, 1);
+    assert_eq!(headless.app().dropped, ["a.txt text/plain", "b.txt text/plain", "c.txt text/plain"]);
+  }
+}
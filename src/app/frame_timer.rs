@@ -0,0 +1,307 @@
+
+use std::{ops::Range, sync::Arc};
+use winit::event::WindowEvent;
+use crate::{*, time::*, timer::Clock};
+use super::{WindowCtx, FramePacing, Throttle, ThrottlePolicy};
+
+
+// fixed timestep, independent of redraws
+
+struct FixedUpdate {
+  step: Duration,
+  next: Instant,
+  count: u64,
+}
+
+
+// per window frame timer
+
+pub(super) struct FrameTimer {
+  animate: DetectChanges<bool>,
+  requested: DetectChanges<Option<Duration>>,
+  last: Instant,
+  next: Instant,
+  deadline: Option<Instant>,
+  fixed_update: DetectChanges<Option<Duration>>,
+  fixed: Option<FixedUpdate>,
+  pacing: DetectChanges<FramePacing>,
+  fixed_duration: Option<Duration>, // the duration before Display pacing replaced it
+  occluded: bool,
+  minimized: bool,
+  focused: bool,
+  throttle: Throttle,
+  clock: Arc<dyn Clock>,
+}
+
+impl FrameTimer {
+
+  pub(super) fn new(window_ctx: &WindowCtx, clock: Arc<dyn Clock>) -> Self {
+    Self {
+      animate: DetectChanges::new(!window_ctx.animate),
+      requested: DetectChanges::new(None),
+      last: clock.now(),
+      next: clock.now() + window_ctx.duration,
+      deadline: None,
+      fixed_update: DetectChanges::new(None),
+      fixed: None,
+      pacing: DetectChanges::new(FramePacing::Fixed),
+      fixed_duration: None,
+      occluded: false,
+      minimized: false,
+      focused: true,
+      throttle: Throttle::Off,
+      clock,
+    }
+  }
+
+  pub(super) fn deadline(&self) -> Option<Instant> {
+    match (self.deadline, self.fixed.as_ref().map(|fixed| fixed.next)) {
+      (Some(redraw), Some(update)) => Some(redraw.min(update)),
+      (redraw, update) => redraw.or(update),
+    }
+  }
+
+  // the frame duration with reduced throttling
+  fn duration(&self, window_ctx: &WindowCtx) -> Duration {
+    match self.throttle {
+      Throttle::Reduce { duration } => window_ctx.duration.max(duration),
+      _ => window_ctx.duration,
+    }
+  }
+
+  fn set_earlier(&mut self, instant: Instant) {
+    self.deadline = Some(match self.deadline {
+      Some(other) => instant.min(other),
+      None => instant,
+    });
+  }
+
+  pub(super) fn check_deadline(&mut self, window_ctx: &WindowCtx, now: Instant) {
+    if self.deadline.is_some_and(|deadline| deadline <= now) {
+      self.deadline = None;
+      window_ctx.request_redraw();
+    }
+  }
+
+  // tracks visibility and focus, on web Occluded is also sent for hidden tabs
+  pub(super) fn window_event(&mut self, event: &WindowEvent) {
+    match event {
+      WindowEvent::Occluded(occluded) => self.occluded = *occluded,
+      WindowEvent::Focused(focused) => self.focused = *focused,
+      WindowEvent::Resized(size) => self.minimized = size.width == 0 || size.height == 0,
+      _ => {},
+    }
+  }
+
+  // returns the new throttle if it changed
+  pub(super) fn update_throttle(&mut self, window_ctx: &mut WindowCtx, policy: &ThrottlePolicy) -> Option<Throttle> {
+
+    let throttle = if self.occluded || self.minimized { policy.hidden }
+    else if !self.focused { policy.unfocused }
+    else { Throttle::Off };
+
+    if throttle == self.throttle { return None }
+
+    let paused = self.throttle == Throttle::Pause;
+    self.throttle = throttle;
+
+    if throttle == Throttle::Pause {
+      self.deadline = None;
+      window_ctx.frame_stats.pause();
+    }
+    else if paused && window_ctx.animate {
+      self.next = self.clock.now(); // resume right away
+      window_ctx.request_redraw();
+    }
+
+    Some(throttle)
+  }
+
+  // call when the monitor may have changed
+  pub(super) fn update_pacing(&mut self, window_ctx: &mut WindowCtx) {
+    if let FramePacing::Display { divisor } = window_ctx.pacing {
+      if let Some(millihertz) = window_ctx.refresh_rate_millihertz().filter(|millihertz| *millihertz > 0) {
+        self.fixed_duration.get_or_insert(window_ctx.duration);
+        window_ctx.duration = Duration::from_nanos(1_000_000_000_000 * divisor.max(1) as u64 / millihertz as u64);
+      }
+    }
+  }
+
+  // steps of the fixed updates that are due, skips the backlog beyond max_updates
+  pub(super) fn due_updates(&mut self, window_ctx: &WindowCtx, now: Instant) -> Option<(Duration, Range<u64>)> {
+
+    let fixed = self.fixed.as_mut().filter(|fixed| fixed.next <= now)?;
+
+    let behind = ((now - fixed.next).as_nanos() / fixed.step.as_nanos()).saturating_add(1);
+    let steps = behind.min(window_ctx.max_updates as u128) as u32;
+
+    if behind > steps as u128 {
+      fixed.next = now + fixed.step; // avoid the spiral of death
+    }
+    else {
+      fixed.next += fixed.step * steps;
+    }
+
+    let first = fixed.count;
+    fixed.count += steps as u64;
+
+    Some((fixed.step, first..fixed.count))
+  }
+
+  pub(super) fn redraw_requested(&mut self, window_ctx: &mut WindowCtx) {
+
+    let now = self.clock.now();
+
+    let duration = self.duration(window_ctx);
+
+    if window_ctx.animate {
+      window_ctx.frame_stats.frame(now, duration);
+    }
+
+    window_ctx.alpha = self.fixed.as_ref().map_or(1.0, |fixed| {
+      let remaining = fixed.next.saturating_duration_since(now);
+      1.0 - (remaining.as_secs_f32() / fixed.step.as_secs_f32()).min(1.0)
+    });
+
+    window_ctx.request = None;
+    self.requested.set_state(None);
+
+    self.last = if self.next > now || (self.next + duration) <= now {
+      now
+    } else {
+      self.next // avoid timer shifts
+    };
+
+    self.next = self.last + duration;
+
+    self.deadline = if window_ctx.animate && self.throttle != Throttle::Pause { Some(self.next) } else { None };
+  }
+
+  // detect state changes, returns the new animation state if it changed
+  pub(super) fn update(&mut self, window_ctx: &mut WindowCtx) -> Option<bool> {
+
+    let mut animate_change = None;
+
+    if self.pacing.note_change(&window_ctx.pacing) {
+      if let (FramePacing::Fixed, Some(duration)) = (window_ctx.pacing, self.fixed_duration.take()) {
+        window_ctx.duration = duration;
+      }
+      self.update_pacing(window_ctx);
+    }
+
+    if self.fixed_update.note_change(&window_ctx.fixed_update) {
+      let count = self.fixed.as_ref().map_or(0, |fixed| fixed.count);
+      self.fixed = window_ctx.fixed_update.filter(|step| !step.is_zero()).map(|step| {
+        FixedUpdate { step, next: self.clock.now() + step, count }
+      });
+    }
+
+    if self.animate.note_change(&window_ctx.animate) {
+      if window_ctx.animate && self.throttle == Throttle::Pause {
+        window_ctx.request = None;
+        self.requested.set_state(None);
+      }
+      else if window_ctx.animate {
+
+        window_ctx.request = None;
+        self.requested.set_state(None);
+
+        let now = self.clock.now();
+
+        if self.next <= now {
+          self.next = now; // reset frame_timer
+          window_ctx.request_redraw();
+        }
+        else {
+          self.set_earlier(self.next);
+        }
+      }
+      else {
+        self.deadline = None;
+        window_ctx.frame_stats.pause();
+      }
+
+      animate_change = Some(window_ctx.animate);
+    }
+
+    if self.requested.changed(&window_ctx.request) {
+
+      if window_ctx.animate {
+        window_ctx.request = None;
+        self.requested.set_state(None);
+      }
+      else if let Some(delay) = window_ctx.request {
+
+        let earlier = match self.requested.state() {
+          None => {
+            self.requested.set_state(Some(delay));
+            true // is definitely eralier
+          },
+          Some(later) if delay < *later => {
+            self.requested.set_state(Some(delay));
+            true // is earlier, checked
+          },
+          Some(previous) => {
+            window_ctx.request = Some(*previous); // reset to previous
+            false // is not earlier
+          },
+        };
+
+        if earlier {
+          if let Some(instant) = self.last.checked_add(delay) {
+
+            let now = self.clock.now();
+            if self.next < now { self.next = now }
+
+            if instant > self.next {
+              self.set_earlier(instant);
+            }
+            else {
+              self.set_earlier(self.next);
+            }
+          }
+          // else consider as infinite delay, keep waiting
+        }
+      }
+      else {
+        window_ctx.request = *self.requested.state(); // reset to previous
+      }
+    }
+
+    animate_change
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+  use std::sync::Arc;
+  use winit::window::WindowId;
+  use crate::{time::Duration, timer::{Clock, VirtualClock}};
+  use super::{FrameTimer, WindowCtx};
+
+  #[test]
+  fn fixed_update_catch_up() {
+
+    let step = Duration::from_secs(1);
+
+    let mut window_ctx = WindowCtx::headless(WindowId::dummy());
+    window_ctx.fixed_update = Some(step);
+    window_ctx.max_updates = 8;
+
+    let clock = VirtualClock::new();
+    let start = clock.now();
+
+    let mut frame_timer = FrameTimer::new(&window_ctx, Arc::new(clock.clone()));
+    frame_timer.update(&mut window_ctx);
+
+    assert!(frame_timer.due_updates(&window_ctx, start).is_none());
+    assert_eq!(frame_timer.due_updates(&window_ctx, start + step * 3 + step / 2), Some((step, 0..3)));
+
+    // long stall
+    let stalled = start + step * 100 + step / 2;
+    assert_eq!(frame_timer.due_updates(&window_ctx, stalled), Some((step, 3..11)));
+    assert!(frame_timer.deadline().is_some_and(|deadline| deadline == stalled + step));
+  }
+}
@@ -0,0 +1,341 @@
+
+use std::{cell::Cell, sync::Arc};
+use winit::{
+  window::{WindowId, WindowAttributes},
+  event::{WindowEvent, StartCause, DeviceId, ElementState, MouseButton, Ime},
+  event_loop::ControlFlow, dpi::{PhysicalSize, PhysicalPosition},
+};
+use crate::{*, timer::{Clock, SystemClock}};
+use super::{AppHandler, AppEvent, AppCtx, WindowCtx, AppState, EventLoopTarget};
+
+
+// stand-in for the event loop
+
+#[derive(Debug)]
+struct HeadlessTarget {
+  exit: Cell<bool>,
+  control_flow: Cell<ControlFlow>,
+  window_count: Cell<u64>,
+}
+
+impl HeadlessTarget {
+
+  fn new() -> Self {
+    Self { exit: Cell::new(false), control_flow: Cell::new(ControlFlow::Wait), window_count: Cell::new(0) }
+  }
+
+  fn next_window_id(&self) -> WindowId {
+    self.window_count.set(self.window_count.get() + 1);
+    WindowId::from(self.window_count.get())
+  }
+}
+
+impl ControlFlowExtension for HeadlessTarget {
+
+  fn set_poll(&self) { self.control_flow.set(ControlFlow::Poll) }
+  fn set_wait(&self) { self.control_flow.set(ControlFlow::Wait) }
+  fn set_wait_until(&self, instant: time::Instant) { self.control_flow.set(ControlFlow::WaitUntil(instant)) }
+
+  fn set_earlier(&self, instant: time::Instant) {
+    match self.control_flow.get() {
+      ControlFlow::Poll => {},
+      ControlFlow::Wait => self.set_wait_until(instant),
+      ControlFlow::WaitUntil(other) => self.set_wait_until(instant.min(other)),
+    }
+  }
+}
+
+impl EventLoopTarget for HeadlessTarget {
+  fn exit(&self) { self.exit.set(true) }
+  fn create_window(&self, _window_attributes: WindowAttributes) -> WindowCtx {
+    WindowCtx::headless(self.next_window_id())
+  }
+}
+
+
+// drives an AppHandler without event loop and windows
+// no events are sent after init, script them including Resumed and the initial Resized
+// winit's KeyEvent can't be constructed outside of winit, use text_input for scripted text
+
+pub struct HeadlessApp<App: AppHandler<T>, T: 'static = ()> {
+  state: AppState<App, T>,
+  target: HeadlessTarget,
+}
+
+impl<App: AppHandler<T>, T: 'static> HeadlessApp<App, T> {
+
+  pub async fn init(init_data: App::InitData) -> Self {
+    Self::init_with_clock(init_data, SystemClock).await
+  }
+
+  // e.g. a VirtualClock to step the frame timer and timers, async tasks still use the system time
+  pub async fn init_with_clock(init_data: App::InitData, clock: impl Clock + 'static) -> Self {
+    let target = HeadlessTarget::new();
+    let mut app_ctx = AppCtx::new(None, WindowCtx::headless(target.next_window_id()));
+    app_ctx.set_clock(Arc::new(clock));
+    let app = App::init(&mut app_ctx, init_data).await;
+    Self { state: AppState::new(app_ctx, app), target }
+  }
+
+  #[cfg(not(target_family="wasm"))]
+  pub fn new(init_data: App::InitData) -> Self {
+    pollster::block_on(Self::init(init_data))
+  }
+
+  #[cfg(not(target_family="wasm"))]
+  pub fn with_clock(init_data: App::InitData, clock: impl Clock + 'static) -> Self {
+    pollster::block_on(Self::init_with_clock(init_data, clock))
+  }
+
+  pub fn app(&self) -> &App { self.state.app() }
+  pub fn app_mut(&mut self) -> &mut App { self.state.app_mut() }
+
+  pub fn app_ctx(&self) -> &AppCtx<T> { self.state.app_ctx() }
+  pub fn app_ctx_mut(&mut self) -> &mut AppCtx<T> { self.state.app_ctx_mut() }
+
+  pub fn main_window_id(&self) -> WindowId {
+    self.app_ctx().main_window_id()
+  }
+
+
+  // scripted events, each followed by AboutToWait like in the event loop, ignored after exit
+
+  pub fn event(&mut self, event: AppEvent<T>) -> &mut Self {
+    if !self.exit_requested() {
+      self.state.dispatch(event, &self.target);
+      self.state.event(PlatformEvent::AboutToWait, &self.target);
+    }
+    self
+  }
+
+  pub fn events(&mut self, events: impl IntoIterator<Item=AppEvent<T>>) -> &mut Self {
+    for event in events { self.event(event); }
+    self
+  }
+
+  // delivered together with one AboutToWait at the end, like events arriving in one go
+  pub fn batch(&mut self, events: impl IntoIterator<Item=AppEvent<T>>) -> &mut Self {
+    if !self.exit_requested() {
+      for event in events {
+        self.state.dispatch(event, &self.target);
+        if self.exit_requested() { return self }
+      }
+      self.state.event(PlatformEvent::AboutToWait, &self.target);
+    }
+    self
+  }
+
+  pub fn window_event(&mut self, window_id: WindowId, event: WindowEvent) -> &mut Self {
+    self.event(AppEvent::WindowEvent { window_id, event })
+  }
+
+  pub fn user_event(&mut self, user_event: T) -> &mut Self {
+    self.event(AppEvent::User(user_event))
+  }
+
+  fn main_window_event(&mut self, event: WindowEvent) -> &mut Self {
+    self.window_event(self.main_window_id(), event)
+  }
+
+  // wake up as the event loop would, fires elapsed timers and deadlines, runs ready local tasks and wakes the app
+  pub fn poll(&mut self) -> &mut Self {
+    if !self.exit_requested() {
+      #[cfg(not(target_family="wasm"))] {
+        crate::platform::executor::fire_timers(crate::time::Instant::now());
+        crate::platform::executor::run_ready();
+      }
+      self.state.wake(&self.target);
+      self.state.event(PlatformEvent::NewEvents(StartCause::Poll), &self.target);
+      self.state.event(PlatformEvent::AboutToWait, &self.target);
+    }
+    self
+  }
+
+  pub fn resumed(&mut self) -> &mut Self { self.event(AppEvent::Resumed) }
+
+  pub fn suspended(&mut self) -> &mut Self { self.event(AppEvent::Suspended) }
+
+  pub fn resize(&mut self, width: u32, height: u32) -> &mut Self {
+    self.main_window_event(WindowEvent::Resized(PhysicalSize::new(width, height)))
+  }
+
+  pub fn focus(&mut self, focused: bool) -> &mut Self {
+    self.main_window_event(WindowEvent::Focused(focused))
+  }
+
+  pub fn cursor_moved(&mut self, x: f64, y: f64) -> &mut Self {
+    self.main_window_event(WindowEvent::CursorMoved { device_id: DeviceId::dummy(), position: PhysicalPosition::new(x, y) })
+  }
+
+  pub fn mouse_input(&mut self, state: ElementState, button: MouseButton) -> &mut Self {
+    self.main_window_event(WindowEvent::MouseInput { device_id: DeviceId::dummy(), state, button })
+  }
+
+  pub fn text_input(&mut self, text: &str) -> &mut Self {
+    self.main_window_event(WindowEvent::Ime(Ime::Commit(text.to_string())))
+  }
+
+  pub fn redraw(&mut self) -> &mut Self {
+    self.main_window_event(WindowEvent::RedrawRequested)
+  }
+
+  pub fn close(&mut self) -> &mut Self {
+    self.main_window_event(WindowEvent::CloseRequested)
+  }
+
+
+  // what the app asked for
+
+  pub fn exit_requested(&self) -> bool {
+    self.target.exit.get()
+  }
+
+  pub fn control_flow(&self) -> ControlFlow {
+    self.target.control_flow.get()
+  }
+
+  // resets the request
+  pub fn take_redraw_request(&self, window_id: WindowId) -> bool {
+    self.app_ctx().window_ctx(window_id).is_some_and(WindowCtx::take_redraw_request)
+  }
+
+  pub fn redraw_requested(&self) -> bool {
+    self.take_redraw_request(self.main_window_id())
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+  use winit::{window::WindowAttributes, event::WindowEvent};
+  use crate::{AppHandler, AppCtx, AppEvent};
+  use super::HeadlessApp;
+
+  #[derive(Default)]
+  struct Counter { text: String, redraws: usize, windows: usize, timers: Vec<crate::TimerId> }
+
+  impl AppHandler for Counter {
+
+    type InitData = ();
+
+    async fn init(_app_ctx: &mut AppCtx, _init_data: ()) -> Self { Self::default() }
+
+    fn event(&mut self, app_ctx: &mut AppCtx, event: &AppEvent) {
+      match event {
+        AppEvent::WindowOpened {..} => self.windows += 1,
+        AppEvent::WindowClosed {..} => self.windows -= 1,
+        AppEvent::WindowEvent { event: WindowEvent::Ime(winit::event::Ime::Commit(text)), .. } => {
+          self.text.push_str(text);
+          if text == "w" { app_ctx.open_window(WindowAttributes::default()); }
+        },
+        AppEvent::WindowEvent { event: WindowEvent::RedrawRequested, .. } => self.redraws += 1,
+        AppEvent::Timer(timer_id) => self.timers.push(*timer_id),
+        AppEvent::WindowEvent { event: WindowEvent::CloseRequested, .. } if self.text == "keep" => app_ctx.veto_close(),
+        _ => {},
+      }
+    }
+  }
+
+  #[test]
+  fn scripted_events() {
+
+    let mut headless = HeadlessApp::<Counter>::new(());
+
+    headless.resumed().resize(800, 600);
+    assert!(headless.redraw_requested());
+    assert!(!headless.redraw_requested());
+
+    headless.redraw().text_input("ab").text_input("w");
+    assert_eq!(headless.app().text, "abw");
+    assert_eq!(headless.app().redraws, 1);
+    assert_eq!(headless.app().windows, 1);
+    assert_eq!(headless.app_ctx().windows().count(), 2);
+
+    let window_id = headless.app_ctx().windows().map(|window_ctx| window_ctx.id())
+      .find(|id| *id != headless.main_window_id()).unwrap();
+
+    headless.window_event(window_id, WindowEvent::CloseRequested);
+    assert_eq!(headless.app().windows, 0);
+    assert!(!headless.exit_requested());
+
+    headless.close();
+    assert!(headless.exit_requested());
+
+    headless.redraw(); // ignored after exit
+    assert_eq!(headless.app().redraws, 1);
+  }
+
+  #[test]
+  fn vetoed_close() {
+
+    let mut headless = HeadlessApp::<Counter>::new(());
+
+    headless.resumed().text_input("keep").close();
+    assert!(!headless.exit_requested());
+
+    headless.app_mut().text.clear();
+    headless.app_ctx_mut().unsaved_changes = true;
+    headless.close();
+    assert!(!headless.exit_requested()); // needs confirmation
+
+    headless.text_input("w");
+    let window_id = headless.app_ctx().windows().map(|window_ctx| window_ctx.id())
+      .find(|id| *id != headless.main_window_id()).unwrap();
+    headless.window_event(window_id, WindowEvent::CloseRequested);
+    assert_eq!(headless.app().windows, 0); // only the main window is guarded
+
+    let main_window_id = headless.main_window_id();
+    headless.app_ctx_mut().close_window(main_window_id);
+    headless.poll();
+    assert!(headless.exit_requested());
+  }
+
+  #[cfg(feature = "frame_timer")]
+  #[test]
+  fn throttle_hidden() {
+
+    use winit::event_loop::ControlFlow;
+
+    let mut headless = HeadlessApp::<Counter>::new(());
+    let window_id = headless.main_window_id();
+
+    headless.resumed();
+    headless.app_ctx_mut().animate = true;
+    headless.poll();
+    assert!(matches!(headless.control_flow(), ControlFlow::WaitUntil(_)));
+
+    headless.window_event(window_id, WindowEvent::Occluded(true));
+    assert_eq!(headless.control_flow(), ControlFlow::Wait); // paused
+
+    headless.window_event(window_id, WindowEvent::Occluded(false));
+    assert!(headless.redraw_requested()); // resumed right away
+  }
+
+  #[test]
+  fn timers() {
+
+    use winit::event_loop::ControlFlow;
+    use crate::{time::Duration, timer::VirtualClock};
+
+    let clock = VirtualClock::new();
+    let mut headless = HeadlessApp::<Counter>::with_clock((), clock.clone());
+
+    let timeout = headless.app_ctx_mut().set_timeout(Duration::ZERO);
+    let interval = headless.app_ctx_mut().set_interval(Duration::from_millis(1));
+    let cancelled = headless.app_ctx_mut().set_timeout(Duration::ZERO);
+    assert!(headless.app_ctx_mut().cancel_timer(cancelled));
+
+    headless.poll();
+    assert_eq!(headless.app().timers, [timeout]);
+    assert!(matches!(headless.control_flow(), ControlFlow::WaitUntil(_)));
+
+    clock.advance(Duration::from_millis(1));
+    headless.poll();
+    assert_eq!(headless.app().timers, [timeout, interval]);
+
+    assert!(headless.app_ctx_mut().cancel_timer(interval));
+    headless.poll();
+    assert_eq!(headless.control_flow(), ControlFlow::Wait);
+  }
+}
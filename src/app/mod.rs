@@ -1,7 +1,11 @@
 
 use std::future::Future;
 use std::pin::Pin;
-use winit::event::WindowEvent;
+use std::marker::PhantomData;
+use winit::{event::{WindowEvent, DeviceEvent, DeviceId}, window::WindowId};
+
+#[cfg(feature = "file_drop")]
+use winit::dpi::PhysicalPosition;
 use crate::*;
 
 // mods
@@ -12,11 +16,64 @@ pub use ctx::*;
 use state::*;
 pub use mount::*;
 
+#[cfg(feature = "frame_timer")]
+mod frame_timer;
+
+#[cfg(feature = "frame_timer")]
+use frame_timer::*;
+
+#[cfg(target_family="wasm")]
+mod before_unload;
+
+#[cfg(target_family="wasm")]
+use before_unload::*;
+
+pub mod headless;
+
+mod async_app;
+pub use async_app::*;
+
+#[cfg(feature = "window_geometry")]
+mod window_geometry;
+
+#[cfg(feature = "window_geometry")]
+pub use window_geometry::*;
+
+#[cfg(feature = "settings")]
+mod settings_saver;
+
+#[cfg(feature = "settings")]
+use settings_saver::SettingsSaver;
+
+#[cfg(feature = "recorder")]
+mod recorder;
+
+#[cfg(feature = "recorder")]
+pub use recorder::*;
+
 
 #[cfg(all(feature = "web_clipboard", target_family="wasm"))]
 #[cfg(web_sys_unstable_apis)]
 pub mod web_clipboard;
 
+#[cfg(feature = "clipboard")]
+pub mod clipboard;
+
+#[cfg(feature = "file_drop")]
+mod file_drop;
+
+#[cfg(feature = "file_drop")]
+use file_drop::FileDropTracker;
+
+#[cfg(feature = "file_drop")]
+pub use file_drop::DroppedFile;
+
+#[cfg(feature = "file_dialog")]
+mod file_dialog;
+
+#[cfg(feature = "file_dialog")]
+pub use file_dialog::*;
+
 
 // exports
 #[cfg(feature = "frame_timer")]
@@ -25,51 +82,112 @@ use crate::time::Duration;
 #[cfg(feature = "frame_timer")]
 pub const STD_DURATION: Duration = Duration::from_nanos(1_000_000_000/60);
 
+// Fixed keeps the duration, Display sets it to divisor refresh intervals of the current monitor
+// falls back to the duration if the refresh rate is unknown, e.g. on web, switching back to Fixed restores it
+// the monitor is checked again on Moved and ScaleFactorChanged, Wayland sends no Moved,
+// so there a window moved to a monitor with the same scale keeps the old rate
+#[cfg(feature = "frame_timer")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FramePacing {
+  #[default] Fixed,
+  Display { divisor: u32 },
+}
+
+// animation while a window is hidden or unfocused, Off keeps going, Reduce caps the frame rate
+#[cfg(feature = "frame_timer")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Throttle {
+  #[default] Off,
+  Reduce { duration: Duration },
+  Pause,
+}
+
+// hidden covers occluded and minimized windows, and hidden browser tabs
+#[cfg(feature = "frame_timer")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThrottlePolicy {
+  pub hidden: Throttle,
+  pub unfocused: Throttle,
+}
+
+#[cfg(feature = "frame_timer")]
+impl Default for ThrottlePolicy {
+  fn default() -> Self { Self { hidden: Throttle::Pause, unfocused: Throttle::Off } }
+}
+
+// fixed updates per wake up, further missed steps are skipped
+#[cfg(feature = "frame_timer")]
+pub const STD_MAX_UPDATES: u32 = 8;
+
 
 #[derive(Debug, Clone)]
-pub enum AppEvent {
+pub enum AppEvent<T = ()> {
   Resumed,
   Suspended,
-  WindowEvent(WindowEvent),
-  #[cfg(all(feature = "web_clipboard", target_family="wasm"))] ClipboardFetch,
+  AboutToWait,
+  Exiting,
+  MemoryWarning,
+  WindowOpened { request: WindowRequest, window_id: WindowId },
+  WindowClosed { window_id: WindowId },
+  WindowEvent { window_id: WindowId, event: WindowEvent },
+  #[cfg(feature = "recorder")] ReplayedKeyboardInput { window_id: WindowId, input: RecordedKeyboardInput }, // in place of WindowEvent::KeyboardInput
+  DeviceEvent { device_id: DeviceId, event: DeviceEvent },
+  #[cfg(feature = "frame_timer")] Update { window_id: WindowId, dt: Duration, step: u64 },
+  #[cfg(feature = "frame_timer")] ThrottleChanged { window_id: WindowId, throttle: Throttle },
+  Timer(TimerId),
+  #[cfg(feature = "settings")] SettingsChanged { keys: Vec<String> },
+  User(T),
+  #[cfg(any(feature = "clipboard", all(feature = "web_clipboard", target_family="wasm")))] ClipboardFetch,
   #[cfg(all(feature = "web_clipboard", target_family="wasm"))] ClipboardPaste,
+  #[cfg(all(feature = "web_clipboard", target_family="wasm"))] ClipboardCopy,
+  #[cfg(all(feature = "web_clipboard", target_family="wasm"))] ClipboardCut,
+  #[cfg(all(feature = "web_clipboard", target_family="wasm", web_sys_unstable_apis))] ClipboardError(web_clipboard::ClipboardError),
+  #[cfg(feature = "file_drop")] FileHoverEntered { window_id: WindowId, position: Option<PhysicalPosition<f64>> },
+  #[cfg(feature = "file_drop")] FileHoverLeft { window_id: WindowId },
+  // on native the position is the last CursorMoved, many systems don't send any during a drag, so it may be stale or None
+  #[cfg(feature = "file_drop")] FilesDropped { window_id: WindowId, files: Vec<DroppedFile>, position: Option<PhysicalPosition<f64>> },
+  #[cfg(feature = "file_dialog")] FileDialog { id: FileDialogId, result: FileDialogResult },
 }
 
 
-pub trait AppHandler: Sized + 'static {
+pub trait AppHandler<T: 'static = ()>: Sized + 'static {
 
   type InitData;
 
-  fn init(app_ctx: &mut AppCtx, init_data: Self::InitData) -> impl Future<Output=Self>;
+  fn init(app_ctx: &mut AppCtx<T>, init_data: Self::InitData) -> impl Future<Output=Self>;
+
+  fn event(&mut self, app_ctx: &mut AppCtx<T>, event: &AppEvent<T>);
 
-  fn event(&mut self, app_ctx: &mut AppCtx, event: &AppEvent);
+  // called for PlatformEventExt::AppWake, used by AsyncApp to poll the app future
+  fn wake(&mut self, _app_ctx: &mut AppCtx<T>) {}
 }
 
 
 
 // wrapper for fn-handler types
 
-pub struct AppClosure<H> where
-  H: FnMut(&mut AppCtx, &AppEvent) + Sized + 'static
+pub struct AppClosure<H, T: 'static = ()> where
+  H: FnMut(&mut AppCtx<T>, &AppEvent<T>) + Sized + 'static
 {
   handler: H,
+  user_event: PhantomData<fn(T)>,
 }
 
 
 type BoxFuture<'a, T> = Pin<Box<dyn Future<Output=T> + 'a>>;
 
 
-impl <H> AppHandler for AppClosure<H> where
-  H: FnMut(&mut AppCtx, &AppEvent) + Sized + 'static
+impl <H, T: 'static> AppHandler<T> for AppClosure<H, T> where
+  H: FnMut(&mut AppCtx<T>, &AppEvent<T>) + Sized + 'static
 {
 
-  type InitData = Box<dyn FnOnce(&mut AppCtx) -> BoxFuture<'_, H>>;
+  type InitData = Box<dyn FnOnce(&mut AppCtx<T>) -> BoxFuture<'_, H>>;
 
-  async fn init(app_ctx: &mut AppCtx, init_fn: Self::InitData) -> Self {
-    Self { handler: init_fn(app_ctx).await }
+  async fn init(app_ctx: &mut AppCtx<T>, init_fn: Self::InitData) -> Self {
+    Self { handler: init_fn(app_ctx).await, user_event: PhantomData }
   }
 
-  fn event(&mut self, app_ctx: &mut AppCtx, event: &AppEvent) {
+  fn event(&mut self, app_ctx: &mut AppCtx<T>, event: &AppEvent<T>) {
     (self.handler)(app_ctx, event)
   }
 }
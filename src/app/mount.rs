@@ -1,45 +1,74 @@
 
-use winit::{window::{WindowAttributes, Window, WindowId}, event::{WindowEvent, StartCause}, event_loop::ActiveEventLoop};
+use winit::{window::{WindowAttributes, Window, WindowId}, event::{WindowEvent, DeviceEvent, DeviceId, StartCause}, event_loop::ActiveEventLoop};
 use winit::application::ApplicationHandler;
 use std::sync::mpsc::{Receiver, sync_channel};
 
 use crate::*;
-use super::{AppHandler, AppState, AppCtx};
+use super::{AppHandler, AppState, AppCtx, WindowCtx};
 
+#[cfg(not(target_family="wasm"))]
+use crate::platform::executor;
 
-enum MountState<App: AppHandler> {
+#[cfg(feature = "settings")]
+use crate::settings::Settings;
+
+#[cfg(feature = "window_geometry")]
+use super::WindowGeometry;
+
+
+enum MountState<App: AppHandler<T>, T: UserEvent> {
   Init {
-    event_queue: Vec<PlatformEvent>,
-    window_attributes: WindowAttributes,
-    event_loop_proxy: PlatformEventLoopProxy,
+    event_queue: Vec<PlatformEvent<T>>,
+    window_attributes: Box<WindowAttributes>,
+    event_loop_proxy: PlatformEventLoopProxy<T>,
     init_data: App::InitData,
   },
   Window {
-    event_queue: Vec<PlatformEvent>,
+    event_queue: Vec<PlatformEvent<T>>,
     window: Window,
-    event_loop_proxy: PlatformEventLoopProxy,
+    event_loop_proxy: PlatformEventLoopProxy<T>,
     init_data: App::InitData,
   },
   Mounting {
-    event_queue: Vec<PlatformEvent>,
+    event_queue: Vec<PlatformEvent<T>>,
     window_id: WindowId,
-    receiver: Receiver<AppState<App>>,
+    receiver: Receiver<AppState<App, T>>,
   },
-  Mounted(AppState<App>),
+  Mounted(Box<AppState<App, T>>),
 }
 
 
-pub struct AppMount<App: AppHandler> {
-  state: MountState<App>,
+pub struct AppMount<App: AppHandler<T>, T: UserEvent = ()> {
+  state: MountState<App, T>,
+  #[cfg(feature = "settings")] settings: Option<Settings>,
 }
 
-impl<App: AppHandler> AppMount<App> {
+impl<App: AppHandler<T>, T: UserEvent> AppMount<App, T> {
+
+  pub fn mount(event_loop_proxy: PlatformEventLoopProxy<T>, window_attributes: WindowAttributes, init_data: App::InitData) -> Self {
+
+    #[cfg(not(target_family="wasm"))] {
+      // wake up the event loop for ready local tasks
+      let event_loop_proxy = event_loop_proxy.clone();
+      executor::set_wake_hook(move || {
+        let _res = event_loop_proxy.send_event(PlatformEventExt::Wake);
+      });
+    }
+
+    Self {
+      state: MountState::Init { event_queue: Vec::new(), window_attributes: window_attributes.into(), event_loop_proxy, init_data },
+      #[cfg(feature = "settings")] settings: None,
+    }
+  }
 
-  pub fn mount(event_loop_proxy: PlatformEventLoopProxy, window_attributes: WindowAttributes, init_data: App::InitData) -> Self {
-    Self { state: MountState::Init { event_queue: Vec::new(), window_attributes, event_loop_proxy, init_data } }
+  // become AppCtx::settings, with window_geometry the main window is restored from them
+  #[cfg(feature = "settings")]
+  pub fn with_settings(mut self, settings: Settings) -> Self {
+    self.settings = Some(settings);
+    self
   }
 
-  pub fn run(self, event_loop: PlatformEventLoop) {
+  pub fn run(self, event_loop: PlatformEventLoop<T>) {
 
     #[cfg(not(target_family="wasm"))] {
       let mut app = self;
@@ -57,7 +86,7 @@ impl<App: AppHandler> AppMount<App> {
     Self::mount(event_loop.create_proxy(), window_attributes, init_data).run(event_loop);
   }
 
-  pub fn event(&mut self, event: PlatformEvent, event_loop: &ActiveEventLoop) {
+  pub fn event(&mut self, event: PlatformEvent<T>, event_loop: &ActiveEventLoop) {
 
     match &mut self.state {
 
@@ -74,7 +103,13 @@ impl<App: AppHandler> AppMount<App> {
           take_mut::take(&mut self.state, |state| {
             if let MountState::Init { event_queue, window_attributes, init_data, event_loop_proxy } = state {
 
-              let window = crate::window(event_loop, window_attributes);
+              #[cfg(feature = "window_geometry")]
+              let window_attributes = match self.settings.as_ref().and_then(|settings| settings.get::<WindowGeometry>(WindowGeometry::SETTINGS_KEY)) {
+                Some(geometry) => Box::new(geometry.restore(event_loop, *window_attributes)),
+                None => window_attributes,
+              };
+
+              let window = crate::window(event_loop, *window_attributes);
               mount_window(&window);
 
               MountState::Window { window, event_queue, init_data, event_loop_proxy }
@@ -94,6 +129,9 @@ impl<App: AppHandler> AppMount<App> {
 
           event_queue.push(event);
 
+          #[cfg(feature = "settings")]
+          let settings = self.settings.take();
+
           take_mut::take(&mut self.state, |state| {
             if let MountState::Window { event_queue, window, init_data, event_loop_proxy } = state {
 
@@ -101,11 +139,16 @@ impl<App: AppHandler> AppMount<App> {
               let window_id = window.id();
 
               spawn_local(async move {
-                let mut app_ctx = AppCtx::new(event_loop_proxy.clone(), window);
+                let mut app_ctx = AppCtx::new(Some(event_loop_proxy.clone()), WindowCtx::new(window));
+                #[cfg(feature = "settings")] if let Some(settings) = settings {
+                  app_ctx.settings = settings;
+                }
                 let app = App::init(&mut app_ctx, init_data).await;
                 let app_state = AppState::new(app_ctx, app);
                 sender.send(app_state).unwrap();
-                event_loop_proxy.send_event(PlatformEventExt::AppInit {window_id}).unwrap();
+                if event_loop_proxy.send_event(PlatformEventExt::AppInit {window_id}).is_err() {
+                  log_err!("event loop closed before AppInit");
+                }
               });
 
               MountState::Mounting { event_queue, window_id, receiver }
@@ -128,7 +171,7 @@ impl<App: AppHandler> AppMount<App> {
               app_state.event(event, event_loop);
             }
 
-            self.state = MountState::Mounted(app_state);
+            self.state = MountState::Mounted(Box::new(app_state));
           }
         },
 
@@ -141,7 +184,7 @@ impl<App: AppHandler> AppMount<App> {
 }
 
 
-impl<App: AppHandler> ApplicationHandler<PlatformEventExt> for AppMount<App> {
+impl<App: AppHandler<T>, T: UserEvent> ApplicationHandler<PlatformEventExt<T>> for AppMount<App, T> {
 
   fn new_events(&mut self, event_loop: &ActiveEventLoop, cause: StartCause) {
     self.event(PlatformEvent::NewEvents(cause), event_loop);
@@ -155,7 +198,14 @@ impl<App: AppHandler> ApplicationHandler<PlatformEventExt> for AppMount<App> {
     self.event(PlatformEvent::Suspended, event_loop);
   }
 
-  fn user_event(&mut self, event_loop: &ActiveEventLoop, event: PlatformEventExt) {
+  fn user_event(&mut self, event_loop: &ActiveEventLoop, event: PlatformEventExt<T>) {
+
+    #[cfg(not(target_family="wasm"))]
+    if let PlatformEventExt::Wake = event {
+      executor::run_ready();
+      return;
+    }
+
     self.event(PlatformEvent::UserEvent(event), event_loop);
   }
 
@@ -163,4 +213,36 @@ impl<App: AppHandler> ApplicationHandler<PlatformEventExt> for AppMount<App> {
     self.event(PlatformEvent::WindowEvent { window_id, event }, event_loop);
   }
 
+  fn device_event(&mut self, event_loop: &ActiveEventLoop, device_id: DeviceId, event: DeviceEvent) {
+    self.event(PlatformEvent::DeviceEvent { device_id, event }, event_loop);
+  }
+
+  fn exiting(&mut self, event_loop: &ActiveEventLoop) {
+    self.event(PlatformEvent::LoopExiting, event_loop);
+  }
+
+  fn memory_warning(&mut self, event_loop: &ActiveEventLoop) {
+    self.event(PlatformEvent::MemoryWarning, event_loop);
+  }
+
+  // not queued before the app is mounted
+  fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+
+    #[cfg(not(target_family="wasm"))] {
+      executor::fire_timers(time::Instant::now());
+      executor::run_ready();
+    }
+
+    match &mut self.state {
+      MountState::Mounted(app_state) => app_state.event(PlatformEvent::AboutToWait, event_loop),
+      _ => event_loop.set_wait(),
+    }
+
+    // local timers
+    #[cfg(not(target_family="wasm"))]
+    if let Some(deadline) = executor::next_timer() {
+      event_loop.set_earlier(deadline);
+    }
+  }
+
 }
\ No newline at end of file
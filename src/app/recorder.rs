@@ -0,0 +1,533 @@
+
+use std::{collections::VecDeque, path::PathBuf, marker::PhantomData};
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+use serde_json::Value;
+use winit::{
+  window::{WindowId, Theme},
+  event::{WindowEvent, DeviceEvent, DeviceId, KeyEvent, RawKeyEvent, ElementState, MouseButton, MouseScrollDelta, TouchPhase, Ime},
+  keyboard::{ModifiersState, Key, PhysicalKey, KeyLocation}, dpi::{PhysicalPosition, PhysicalSize},
+};
+use crate::{*, time::{Instant, Duration}};
+use super::{AppHandler, AppEvent, AppCtx};
+
+#[cfg(not(target_family="wasm"))]
+use std::{path::Path, fs::File, io::{Write, BufWriter}};
+
+
+// serializable app events, windows are numbered in opening order with the main window as 0, device ids aren't kept
+// user events are stored as json, so recording needs T: Serialize and replaying T: DeserializeOwned
+// winit's KeyEvent can't be constructed outside of winit, replayed keys arrive as AppEvent::ReplayedKeyboardInput
+// not recorded: AboutToWait, window opening and closing, and events the replayed app produces again itself,
+// like redraws, timers, updates, file dialogs or clipboard fetches
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RecordedEvent {
+  Resumed,
+  Suspended,
+  Exiting,
+  MemoryWarning,
+  Window { window: usize, event: RecordedWindowEvent },
+  Device(RecordedDeviceEvent),
+  User(Value),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RecordedWindowEvent {
+  Resized(PhysicalSize<u32>),
+  Moved(PhysicalPosition<i32>),
+  CloseRequested,
+  Focused(bool),
+  Occluded(bool),
+  ThemeChanged(Theme),
+  CursorMoved(PhysicalPosition<f64>),
+  CursorEntered,
+  CursorLeft,
+  MouseInput { state: ElementState, button: MouseButton },
+  MouseWheel { delta: MouseScrollDelta, phase: TouchPhase },
+  ModifiersChanged(ModifiersState),
+  Ime(Ime),
+  DroppedFile(PathBuf),
+  HoveredFile(PathBuf),
+  HoveredFileCancelled,
+  KeyboardInput(RecordedKeyboardInput),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedKeyboardInput {
+  pub logical_key: Key,
+  pub physical_key: PhysicalKey,
+  pub location: KeyLocation,
+  pub state: ElementState,
+  pub text: Option<String>,
+  pub repeat: bool,
+  pub is_synthetic: bool,
+}
+
+impl RecordedKeyboardInput {
+
+  pub fn from_key_event(event: &KeyEvent, is_synthetic: bool) -> Self {
+    Self {
+      logical_key: event.logical_key.clone(),
+      physical_key: event.physical_key,
+      location: event.location,
+      state: event.state,
+      text: event.text.as_ref().map(|text| text.to_string()),
+      repeat: event.repeat,
+      is_synthetic,
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RecordedDeviceEvent {
+  Added,
+  Removed,
+  MouseMotion { delta: (f64, f64) },
+  MouseWheel { delta: MouseScrollDelta },
+  Motion { axis: u32, value: f64 },
+  Button { button: u32, state: ElementState },
+  Key(RawKeyEvent),
+}
+
+impl RecordedDeviceEvent {
+
+  pub fn from_device_event(event: &DeviceEvent) -> Self {
+    match event {
+      DeviceEvent::Added => Self::Added,
+      DeviceEvent::Removed => Self::Removed,
+      DeviceEvent::MouseMotion { delta } => Self::MouseMotion { delta: *delta },
+      DeviceEvent::MouseWheel { delta } => Self::MouseWheel { delta: *delta },
+      DeviceEvent::Motion { axis, value } => Self::Motion { axis: *axis, value: *value },
+      DeviceEvent::Button { button, state } => Self::Button { button: *button, state: *state },
+      DeviceEvent::Key(raw) => Self::Key(raw.clone()),
+    }
+  }
+
+  pub fn to_device_event(&self) -> DeviceEvent {
+    match self {
+      Self::Added => DeviceEvent::Added,
+      Self::Removed => DeviceEvent::Removed,
+      Self::MouseMotion { delta } => DeviceEvent::MouseMotion { delta: *delta },
+      Self::MouseWheel { delta } => DeviceEvent::MouseWheel { delta: *delta },
+      Self::Motion { axis, value } => DeviceEvent::Motion { axis: *axis, value: *value },
+      Self::Button { button, state } => DeviceEvent::Button { button: *button, state: *state },
+      Self::Key(raw) => DeviceEvent::Key(raw.clone()),
+    }
+  }
+}
+
+impl RecordedWindowEvent {
+
+  pub fn from_window_event(event: &WindowEvent) -> Option<Self> {
+    Some(match event {
+      WindowEvent::Resized(size) => Self::Resized(*size),
+      WindowEvent::Moved(position) => Self::Moved(*position),
+      WindowEvent::CloseRequested => Self::CloseRequested,
+      WindowEvent::Focused(focused) => Self::Focused(*focused),
+      WindowEvent::Occluded(occluded) => Self::Occluded(*occluded),
+      WindowEvent::ThemeChanged(theme) => Self::ThemeChanged(*theme),
+      WindowEvent::CursorMoved { position, .. } => Self::CursorMoved(*position),
+      WindowEvent::CursorEntered { .. } => Self::CursorEntered,
+      WindowEvent::CursorLeft { .. } => Self::CursorLeft,
+      WindowEvent::MouseInput { state, button, .. } => Self::MouseInput { state: *state, button: *button },
+      WindowEvent::MouseWheel { delta, phase, .. } => Self::MouseWheel { delta: *delta, phase: *phase },
+      WindowEvent::ModifiersChanged(modifiers) => Self::ModifiersChanged(modifiers.state()),
+      WindowEvent::Ime(ime) => Self::Ime(ime.clone()),
+      WindowEvent::DroppedFile(path) => Self::DroppedFile(path.clone()),
+      WindowEvent::HoveredFile(path) => Self::HoveredFile(path.clone()),
+      WindowEvent::HoveredFileCancelled => Self::HoveredFileCancelled,
+      WindowEvent::KeyboardInput { event, is_synthetic, .. } => Self::KeyboardInput(RecordedKeyboardInput::from_key_event(event, *is_synthetic)),
+      _ => return None,
+    })
+  }
+
+  // None for keyboard input
+  pub fn to_window_event(&self) -> Option<WindowEvent> {
+    let device_id = DeviceId::dummy();
+    Some(match self {
+      Self::Resized(size) => WindowEvent::Resized(*size),
+      Self::Moved(position) => WindowEvent::Moved(*position),
+      Self::CloseRequested => WindowEvent::CloseRequested,
+      Self::Focused(focused) => WindowEvent::Focused(*focused),
+      Self::Occluded(occluded) => WindowEvent::Occluded(*occluded),
+      Self::ThemeChanged(theme) => WindowEvent::ThemeChanged(*theme),
+      Self::CursorMoved(position) => WindowEvent::CursorMoved { device_id, position: *position },
+      Self::CursorEntered => WindowEvent::CursorEntered { device_id },
+      Self::CursorLeft => WindowEvent::CursorLeft { device_id },
+      Self::MouseInput { state, button } => WindowEvent::MouseInput { device_id, state: *state, button: *button },
+      Self::MouseWheel { delta, phase } => WindowEvent::MouseWheel { device_id, delta: *delta, phase: *phase },
+      Self::ModifiersChanged(state) => WindowEvent::ModifiersChanged((*state).into()),
+      Self::Ime(ime) => WindowEvent::Ime(ime.clone()),
+      Self::DroppedFile(path) => WindowEvent::DroppedFile(path.clone()),
+      Self::HoveredFile(path) => WindowEvent::HoveredFile(path.clone()),
+      Self::HoveredFileCancelled => WindowEvent::HoveredFileCancelled,
+      Self::KeyboardInput(_) => return None,
+    })
+  }
+}
+
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedEntry {
+  pub time: Duration, // since the start of the recording
+  pub event: RecordedEvent,
+}
+
+// stored as json lines, one entry per line
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Recording {
+  pub entries: Vec<RecordedEntry>,
+}
+
+impl Recording {
+
+  pub fn from_json_lines(json_lines: &str) -> anyhow::Result<Self> {
+    let entries = json_lines.lines().filter(|line| !line.trim().is_empty())
+      .map(serde_json::from_str).collect::<Result<_, _>>()?;
+    Ok(Self { entries })
+  }
+
+  pub fn to_json_lines(&self) -> String {
+    self.entries.iter().filter_map(|entry| serde_json::to_string(entry).inspect_err(|err| log_err!(err)).ok())
+      .map(|line| line + "\n").collect()
+  }
+
+  // on web use the json lines, e.g. with the file dialogs
+  #[cfg(not(target_family="wasm"))]
+  pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+    Self::from_json_lines(&std::fs::read_to_string(path)?)
+  }
+
+  #[cfg(not(target_family="wasm"))]
+  pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+    Ok(std::fs::write(path, self.to_json_lines())?)
+  }
+
+  pub fn duration(&self) -> Duration {
+    self.entries.last().map_or(Duration::ZERO, |entry| entry.time)
+  }
+}
+
+
+// records the events the app receives, on native optionally streamed to a file, so crashes keep the recording
+// the file is flushed every FLUSH_INTERVAL of recording time and on exit, a crash loses at most the entries since
+
+#[cfg(not(target_family="wasm"))]
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct RecorderInit<D> {
+  pub init_data: D,
+  #[cfg(not(target_family="wasm"))] pub file: Option<PathBuf>,
+}
+
+impl<D> RecorderInit<D> {
+
+  pub fn new(init_data: D) -> Self {
+    Self { init_data, #[cfg(not(target_family="wasm"))] file: None }
+  }
+
+  #[cfg(not(target_family="wasm"))]
+  pub fn with_file(mut self, path: impl Into<PathBuf>) -> Self {
+    self.file = Some(path.into());
+    self
+  }
+}
+
+pub struct Recorder<App: AppHandler<T>, T: 'static = ()> {
+  app: App,
+  recording: Recording,
+  windows: Vec<WindowId>,
+  start: Instant,
+  #[cfg(not(target_family="wasm"))] file: Option<BufWriter<File>>,
+  #[cfg(not(target_family="wasm"))] flushed: Duration, // recording time of the last flush
+  user_event: PhantomData<fn(T)>,
+}
+
+impl<App: AppHandler<T>, T: Serialize + 'static> Recorder<App, T> {
+
+  pub fn app(&self) -> &App { &self.app }
+  pub fn app_mut(&mut self) -> &mut App { &mut self.app }
+
+  pub fn recording(&self) -> &Recording { &self.recording }
+
+  pub fn take_recording(&mut self) -> Recording {
+    std::mem::take(&mut self.recording)
+  }
+
+  fn record(&mut self, app_ctx: &AppCtx<T>, event: &AppEvent<T>) {
+
+    let event = match event {
+      AppEvent::Resumed => RecordedEvent::Resumed,
+      AppEvent::Suspended => RecordedEvent::Suspended,
+      AppEvent::Exiting => RecordedEvent::Exiting,
+      AppEvent::MemoryWarning => RecordedEvent::MemoryWarning,
+      AppEvent::WindowOpened { window_id, .. } => {
+        self.windows.push(*window_id);
+        return;
+      },
+      AppEvent::WindowEvent { window_id, event } => {
+        let Some(window) = self.windows.iter().position(|id| id == window_id) else { return };
+        let Some(event) = RecordedWindowEvent::from_window_event(event) else { return };
+        RecordedEvent::Window { window, event }
+      },
+      AppEvent::ReplayedKeyboardInput { window_id, input } => {
+        let Some(window) = self.windows.iter().position(|id| id == window_id) else { return };
+        RecordedEvent::Window { window, event: RecordedWindowEvent::KeyboardInput(input.clone()) }
+      },
+      AppEvent::DeviceEvent { event, .. } => RecordedEvent::Device(RecordedDeviceEvent::from_device_event(event)),
+      AppEvent::User(user_event) => match serde_json::to_value(user_event) {
+        Ok(value) => RecordedEvent::User(value),
+        Err(err) => { log_err!(err); return },
+      },
+      _ => return,
+    };
+
+    let entry = RecordedEntry { time: app_ctx.now().saturating_duration_since(self.start), event };
+
+    #[cfg(not(target_family="wasm"))]
+    if let Some(file) = self.file.as_mut() {
+      let flush = entry.event == RecordedEvent::Exiting || entry.time >= self.flushed + FLUSH_INTERVAL;
+      if flush { self.flushed = entry.time }
+
+      let res = serde_json::to_string(&entry).map_err(anyhow::Error::from).and_then(|line| {
+        writeln!(file, "{line}")?;
+        if flush { file.flush()? }
+        Ok(())
+      });
+
+      if let Err(err) = res {
+        log_err!(err);
+        self.file = None; // stop streaming
+      }
+    }
+
+    self.recording.entries.push(entry);
+  }
+}
+
+impl<App: AppHandler<T>, T: Serialize + 'static> AppHandler<T> for Recorder<App, T> {
+
+  type InitData = RecorderInit<App::InitData>;
+
+  async fn init(app_ctx: &mut AppCtx<T>, init: Self::InitData) -> Self {
+
+    #[cfg(not(target_family="wasm"))]
+    let file = init.file.and_then(|path| {
+      File::create(path).map(BufWriter::new).inspect_err(|err| log_err!(err)).ok()
+    });
+
+    let app = App::init(app_ctx, init.init_data).await;
+
+    Self {
+      app, #[cfg(not(target_family="wasm"))] file, #[cfg(not(target_family="wasm"))] flushed: Duration::ZERO,
+      recording: Recording::default(),
+      windows: vec![app_ctx.main_window_id()],
+      start: app_ctx.now(),
+      user_event: PhantomData,
+    }
+  }
+
+  fn event(&mut self, app_ctx: &mut AppCtx<T>, event: &AppEvent<T>) {
+    self.record(app_ctx, event);
+    self.app.event(app_ctx, event);
+  }
+
+  fn wake(&mut self, app_ctx: &mut AppCtx<T>) {
+    self.app.wake(app_ctx);
+  }
+}
+
+#[cfg(not(target_family="wasm"))]
+impl<App: AppHandler<T>, T: 'static> Drop for Recorder<App, T> {
+  fn drop(&mut self) {
+    if let Some(file) = self.file.as_mut() {
+      file.flush().unwrap_or_else(|err| log_err!(err));
+    }
+  }
+}
+
+
+// replays through the AppState dispatch, started with AppCtx::replay
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayTiming {
+  Original,
+  AsFastAsPossible,
+}
+
+#[derive(Debug)]
+pub(super) struct Replay<T> {
+  entries: VecDeque<RecordedEntry>,
+  timing: ReplayTiming,
+  start: Instant,
+  windows: Vec<WindowId>,
+  user_event: fn(Value) -> serde_json::Result<T>,
+}
+
+impl<T> Replay<T> {
+
+  pub(super) fn new(recording: Recording, timing: ReplayTiming, start: Instant, main_window_id: WindowId) -> Self where T: DeserializeOwned {
+    Self { entries: recording.entries.into(), timing, start, windows: vec![main_window_id], user_event: serde_json::from_value }
+  }
+
+  pub(super) fn is_finished(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  pub(super) fn window_opened(&mut self, window_id: WindowId) {
+    self.windows.push(window_id);
+  }
+
+  pub(super) fn deadline(&self) -> Option<Instant> {
+    self.entries.front().map(|entry| match self.timing {
+      ReplayTiming::Original => self.start + entry.time,
+      ReplayTiming::AsFastAsPossible => self.start,
+    })
+  }
+
+  // skips events of windows that weren't opened in the replay and user events that don't deserialize
+  pub(super) fn pop_due(&mut self, now: Instant) -> Option<AppEvent<T>> {
+    while self.deadline().is_some_and(|deadline| deadline <= now) {
+      let entry = self.entries.pop_front()?;
+      match entry.event {
+        RecordedEvent::Resumed => return Some(AppEvent::Resumed),
+        RecordedEvent::Suspended => return Some(AppEvent::Suspended),
+        RecordedEvent::Exiting => return Some(AppEvent::Exiting),
+        RecordedEvent::MemoryWarning => return Some(AppEvent::MemoryWarning),
+        RecordedEvent::Window { window, event } => {
+          let Some(window_id) = self.windows.get(window).copied() else { continue };
+          return Some(match (event.to_window_event(), event) {
+            (Some(event), _) => AppEvent::WindowEvent { window_id, event },
+            (None, RecordedWindowEvent::KeyboardInput(input)) => AppEvent::ReplayedKeyboardInput { window_id, input },
+            (None, _) => continue,
+          });
+        },
+        RecordedEvent::Device(event) => {
+          return Some(AppEvent::DeviceEvent { device_id: DeviceId::dummy(), event: event.to_device_event() });
+        },
+        RecordedEvent::User(value) => match (self.user_event)(value) {
+          Ok(user_event) => return Some(AppEvent::User(user_event)),
+          Err(err) => log_err!(err),
+        },
+      }
+    }
+    None
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+  use std::path::PathBuf;
+  use winit::{
+    window::WindowAttributes, event::{WindowEvent, DeviceEvent, DeviceId, Ime, ElementState, MouseButton},
+    keyboard::{Key, PhysicalKey, KeyCode, KeyLocation},
+  };
+  use crate::{AppHandler, AppCtx, AppEvent, headless::HeadlessApp, timer::VirtualClock};
+  use super::*;
+
+  #[derive(Default)]
+  struct Log { events: Vec<String> }
+
+  impl AppHandler for Log {
+
+    type InitData = ();
+
+    async fn init(_app_ctx: &mut AppCtx, _init_data: ()) -> Self { Self::default() }
+
+    fn event(&mut self, app_ctx: &mut AppCtx, event: &AppEvent) {
+      let window = |window_id| if window_id == app_ctx.main_window_id() { "main" } else { "other" };
+      match event {
+        AppEvent::WindowEvent { window_id, event } => {
+          let window = window(*window_id);
+          match event {
+            WindowEvent::Ime(Ime::Commit(text)) => {
+              if text == "w" { app_ctx.open_window(WindowAttributes::default()); }
+              self.events.push(format!("{window} {text}"));
+            },
+            WindowEvent::MouseInput { state, .. } => self.events.push(format!("{window} {state:?}")),
+            WindowEvent::DroppedFile(path) => self.events.push(format!("{window} {}", path.display())),
+            _ => {},
+          }
+        },
+        AppEvent::ReplayedKeyboardInput { window_id, input } => {
+          self.events.push(format!("{} key {:?}", window(*window_id), input.logical_key));
+        },
+        AppEvent::DeviceEvent { event: DeviceEvent::MouseMotion { delta }, .. } => self.events.push(format!("motion {delta:?}")),
+        AppEvent::User(()) => self.events.push("user".to_string()),
+        _ => {},
+      }
+    }
+  }
+
+  #[test]
+  fn record_and_replay() {
+
+    let mut recorded = HeadlessApp::<Recorder<Log>>::new(RecorderInit::new(()));
+
+    recorded.resumed().resize(800, 600).text_input("w").mouse_input(ElementState::Pressed, MouseButton::Left);
+
+    let window_id = recorded.app_ctx().windows().map(|window_ctx| window_ctx.id())
+      .find(|id| *id != recorded.main_window_id()).unwrap();
+
+    recorded.window_event(window_id, WindowEvent::DroppedFile(PathBuf::from("file.txt")));
+    recorded.event(AppEvent::DeviceEvent { device_id: DeviceId::dummy(), event: DeviceEvent::MouseMotion { delta: (1.0, 2.0) } });
+    recorded.user_event(()).redraw();
+    assert!(!recorded.app().recording().to_json_lines().contains("Redraw"));
+
+    let recording = Recording::from_json_lines(&recorded.app().recording().to_json_lines()).unwrap();
+    assert_eq!(&recording, recorded.app().recording());
+
+    let mut replayed = HeadlessApp::<Log>::new(());
+    replayed.app_ctx_mut().replay(recording, ReplayTiming::AsFastAsPossible);
+    replayed.poll();
+
+    assert_eq!(replayed.app().events, ["main w", "main Pressed", "other file.txt", "motion (1.0, 2.0)", "user"]);
+    assert_eq!(replayed.app().events, recorded.app().app().events);
+  }
+
+  #[test]
+  fn replayed_keys() {
+
+    // winit's KeyEvent can't be built here, so the key is written into the recording
+    let key = RecordedKeyboardInput {
+      logical_key: Key::Character("a".into()), physical_key: PhysicalKey::Code(KeyCode::KeyA),
+      location: KeyLocation::Standard, state: ElementState::Pressed, text: Some("a".to_string()), repeat: false, is_synthetic: false,
+    };
+    let event = RecordedEvent::Window { window: 0, event: RecordedWindowEvent::KeyboardInput(key) };
+    let recording = Recording { entries: vec![RecordedEntry { time: Duration::ZERO, event: event.clone() }] };
+    let recording = Recording::from_json_lines(&recording.to_json_lines()).unwrap();
+
+    // recorded again while replayed
+    let mut replayed = HeadlessApp::<Recorder<Log>>::new(RecorderInit::new(()));
+    replayed.app_ctx_mut().replay(recording, ReplayTiming::AsFastAsPossible);
+    replayed.poll();
+
+    assert_eq!(replayed.app().app().events, ["main key Character(\"a\")"]);
+    assert_eq!(replayed.app().recording().entries.iter().map(|entry| &entry.event).collect::<Vec<_>>(), [&event]);
+  }
+
+  #[test]
+  fn streamed_to_file() {
+
+    let path = std::env::temp_dir().join(format!("platform-recording-{}.jsonl", std::process::id()));
+    let clock = VirtualClock::new();
+    let mut recorded = HeadlessApp::<Recorder<Log>>::with_clock(RecorderInit::new(()).with_file(&path), clock.clone());
+
+    // buffered until a flush interval has passed
+    recorded.resumed();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+
+    clock.advance(FLUSH_INTERVAL);
+    recorded.user_event(());
+    assert_eq!(Recording::load(&path).unwrap().entries.len(), 2);
+
+    // the rest on drop
+    recorded.suspended();
+    drop(recorded);
+    assert_eq!(Recording::load(&path).unwrap().entries.len(), 3);
+
+    std::fs::remove_file(&path).unwrap();
+  }
+}
@@ -0,0 +1,110 @@
+
+use crate::{*, time::{Instant, Duration}};
+use super::AppCtx;
+
+
+// saves changed settings once they settle and on exit, so settings changed on every event aren't written each time
+// after a failed write the next attempt waits longer, instead of retrying after every event
+
+const SETTLE_DURATION: Duration = Duration::from_secs(1);
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(5);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+
+#[derive(Default)]
+pub(super) struct SettingsSaver {
+  deadline: Option<Instant>,
+  retry_delay: Option<Duration>, // after a failure
+}
+
+impl SettingsSaver {
+
+  pub(super) fn deadline(&self) -> Option<Instant> {
+    self.deadline
+  }
+
+  pub(super) fn after_event<T: 'static>(&mut self, app_ctx: &AppCtx<T>) {
+    if self.deadline.is_none() && app_ctx.settings.has_unsaved() {
+      self.deadline = Some(app_ctx.now() + SETTLE_DURATION);
+    }
+  }
+
+  pub(super) fn check_deadline<T: 'static>(&mut self, app_ctx: &mut AppCtx<T>, now: Instant) {
+    if self.deadline.is_some_and(|deadline| deadline <= now) {
+      self.save(app_ctx);
+    }
+  }
+
+  pub(super) fn save<T: 'static>(&mut self, app_ctx: &mut AppCtx<T>) {
+
+    self.deadline = None;
+
+    match app_ctx.settings.save() {
+      Ok(()) => self.retry_delay = None,
+      Err(err) => {
+        log_err!(err);
+        let delay = self.retry_delay.map_or(FIRST_RETRY_DELAY, |delay| (delay * 2).min(MAX_RETRY_DELAY));
+        self.retry_delay = Some(delay);
+        self.deadline = Some(app_ctx.now() + delay);
+      },
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+  use winit::event_loop::ControlFlow;
+  use crate::{AppHandler, AppCtx, AppEvent, headless::HeadlessApp, settings::Settings, timer::{Clock, VirtualClock}};
+  use super::{SETTLE_DURATION, FIRST_RETRY_DELAY};
+
+  struct Noop;
+
+  impl AppHandler for Noop {
+
+    type InitData = ();
+
+    async fn init(_app_ctx: &mut AppCtx, _init_data: ()) -> Self { Self }
+
+    fn event(&mut self, _app_ctx: &mut AppCtx, _event: &AppEvent) {}
+  }
+
+  #[test]
+  fn debounced_saves() {
+
+    let dir = std::env::temp_dir().join(format!("platform-settings-saver-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let clock = VirtualClock::new();
+    let mut headless = HeadlessApp::<Noop>::with_clock((), clock.clone());
+
+    // saved once settled
+    let path = dir.join("settings.json");
+    headless.app_ctx_mut().settings = Settings::load_file(&path).unwrap();
+    headless.app_ctx_mut().settings.set("scale", 2).unwrap();
+    headless.poll().cursor_moved(1.0, 1.0);
+    assert!(!path.exists());
+
+    clock.advance(SETTLE_DURATION);
+    headless.poll();
+    assert!(path.exists());
+
+    // a directory in its place, so writing fails
+    let blocked = dir.join("blocked");
+    headless.app_ctx_mut().settings = Settings::load_file(&blocked).unwrap();
+    std::fs::create_dir(&blocked).unwrap();
+    headless.app_ctx_mut().settings.set("scale", 3).unwrap();
+    headless.poll();
+    clock.advance(SETTLE_DURATION);
+    headless.poll().cursor_moved(2.0, 2.0);
+    assert_eq!(headless.control_flow(), ControlFlow::WaitUntil(clock.now() + FIRST_RETRY_DELAY));
+
+    clock.advance(FIRST_RETRY_DELAY);
+    headless.poll();
+    assert_eq!(headless.control_flow(), ControlFlow::WaitUntil(clock.now() + FIRST_RETRY_DELAY * 2));
+    assert!(headless.app_ctx().settings.has_unsaved());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+}
@@ -1,135 +1,351 @@
 
-use winit::{window::WindowId, event::WindowEvent, event_loop::ActiveEventLoop};
+use winit::{window::WindowAttributes, event::WindowEvent, event_loop::ActiveEventLoop};
 use crate::{*};
 
 #[cfg(feature = "frame_timer")]
-use crate::time::*;
-
-#[cfg(feature = "frame_timer")]
-use winit::event::StartCause;
+use {std::collections::HashMap, winit::window::WindowId, crate::time::*, super::FrameTimer};
 
 #[cfg(feature = "auto_wake_lock")]
 use crate::wake_lock::WakeLock;
 
-use super::{AppEvent, AppCtx, AppHandler};
+#[cfg(target_family="wasm")]
+use super::BeforeUnloadListener;
+
+#[cfg(feature = "settings")]
+use super::SettingsSaver;
+
+#[cfg(feature = "window_geometry")]
+use super::GeometryTracker;
+
+#[cfg(feature = "file_drop")]
+use super::FileDropTracker;
+
+use super::{AppEvent, AppCtx, WindowCtx, AppHandler};
+
+
+// what the app state needs from the event loop
+
+pub(super) trait EventLoopTarget: ControlFlowExtension {
+  fn exit(&self);
+  fn create_window(&self, window_attributes: WindowAttributes) -> WindowCtx;
+}
+
+impl EventLoopTarget for ActiveEventLoop {
+  fn exit(&self) { ActiveEventLoop::exit(self) }
+  fn create_window(&self, window_attributes: WindowAttributes) -> WindowCtx {
+    WindowCtx::new(crate::window(self, window_attributes))
+  }
+}
 
 
-pub(super) struct AppState<App: AppHandler> {
+pub(super) struct AppState<App: AppHandler<T>, T: 'static> {
   #[cfg(feature = "auto_wake_lock")] wake_lock: Option<WakeLock>,
-  #[cfg(feature = "frame_timer")] animate: DetectChanges<bool>,
-  #[cfg(feature = "frame_timer")] requested: DetectChanges<Option<Duration>>,
-  #[cfg(feature = "frame_timer")] last: Instant,
-  #[cfg(feature = "frame_timer")] next: Instant,
-  window_id: WindowId,
-  app_ctx: AppCtx,
+  #[cfg(feature = "frame_timer")] frame_timers: HashMap<WindowId, FrameTimer>,
+  #[cfg(target_family="wasm")] before_unload: BeforeUnloadListener,
+  #[cfg(feature = "settings")] settings_saver: SettingsSaver,
+  #[cfg(feature = "window_geometry")] geometry: GeometryTracker,
+  #[cfg(feature = "file_drop")] file_drop: FileDropTracker,
+  app_ctx: AppCtx<T>,
   app: App,
 }
 
-impl<App: AppHandler> AppState<App> {
+impl<App: AppHandler<T>, T: 'static> AppState<App, T> {
 
-  pub(super) fn new(app_ctx: AppCtx, app: App) -> Self {
+  pub(super) fn new(app_ctx: AppCtx<T>, app: App) -> Self {
     Self {
       #[cfg(feature = "auto_wake_lock")] wake_lock: WakeLock::new().inspect_err(|err| log_warn!(err)).ok(),
-      #[cfg(feature = "frame_timer")] animate: DetectChanges::new(!app_ctx.animate),
-      #[cfg(feature = "frame_timer")] requested: DetectChanges::new(None),
-      #[cfg(feature = "frame_timer")] last: Instant::now(),
-      #[cfg(feature = "frame_timer")] next: Instant::now() + app_ctx.duration,
-      window_id: app_ctx.window().id(),
+      #[cfg(feature = "frame_timer")] frame_timers: app_ctx.windows().map(|window_ctx| {
+        (window_ctx.id(), FrameTimer::new(window_ctx, app_ctx.clock().clone()))
+      }).collect(),
+      #[cfg(target_family="wasm")] before_unload: BeforeUnloadListener::new(),
+      #[cfg(feature = "settings")] settings_saver: SettingsSaver::default(),
+      #[cfg(feature = "window_geometry")] geometry: GeometryTracker::new(&app_ctx),
+      #[cfg(feature = "file_drop")] file_drop: FileDropTracker::new(&app_ctx),
       app_ctx, app,
     }
   }
 
-  pub(super) fn event(&mut self, event: PlatformEvent, event_loop: &ActiveEventLoop) {
+  pub(super) fn app(&self) -> &App { &self.app }
+  pub(super) fn app_mut(&mut self) -> &mut App { &mut self.app }
+
+  pub(super) fn app_ctx(&self) -> &AppCtx<T> { &self.app_ctx }
+  pub(super) fn app_ctx_mut(&mut self) -> &mut AppCtx<T> { &mut self.app_ctx }
 
-    let app_ctx = &mut self.app_ctx;
+
+  pub(super) fn event(&mut self, event: PlatformEvent<T>, event_loop: &impl EventLoopTarget) {
 
     match event {
 
-      #[cfg(feature = "frame_timer")]
-      PlatformEvent::NewEvents(StartCause::ResumeTimeReached {..}) => {
-        app_ctx.window().request_redraw();
+      PlatformEvent::NewEvents(_) => {
+        let now = self.app_ctx.now();
+
+        #[cfg(feature = "frame_timer")] {
+          for (window_id, frame_timer) in &mut self.frame_timers {
+            if let Some(window_ctx) = self.app_ctx.window_ctx(*window_id) {
+              frame_timer.check_deadline(window_ctx, now);
+            }
+          }
+          let window_ids: Vec<WindowId> = self.frame_timers.keys().copied().collect();
+          for window_id in window_ids {
+            self.fixed_updates(window_id, now, event_loop);
+          }
+        }
+
+        #[cfg(feature = "window_geometry")]
+        self.geometry.check_deadline(&mut self.app_ctx, now);
+
+        #[cfg(feature = "settings")]
+        self.settings_saver.check_deadline(&mut self.app_ctx, now);
+
+        while !self.app_ctx.exit {
+          let Some(timer_id) = self.app_ctx.pop_due_timer(now) else { break };
+          self.dispatch(AppEvent::Timer(timer_id), event_loop);
+        }
+
+        #[cfg(feature = "recorder")]
+        while !self.app_ctx.exit {
+          let Some(event) = self.app_ctx.pop_replay_event(now) else { break };
+          self.dispatch(event, event_loop);
+        }
+      },
+
+      PlatformEvent::AboutToWait => {
+        #[cfg(all(feature = "file_drop", not(target_family="wasm")))]
+        for event in self.file_drop.take_events() {
+          if self.app_ctx.exit { break }
+          self.dispatch(event, event_loop);
+        }
+        self.dispatch(AppEvent::AboutToWait, event_loop);
+        self.update_control_flow(event_loop);
+      },
+
+      PlatformEvent::Resumed => self.dispatch(AppEvent::Resumed, event_loop),
+
+      PlatformEvent::Suspended => self.dispatch(AppEvent::Suspended, event_loop),
+
+      PlatformEvent::LoopExiting => {
+        #[cfg(feature = "window_geometry")] self.geometry.save(&mut self.app_ctx);
+        self.dispatch(AppEvent::Exiting, event_loop);
+        #[cfg(feature = "settings")] self.settings_saver.save(&mut self.app_ctx);
+      },
+
+      PlatformEvent::MemoryWarning => self.dispatch(AppEvent::MemoryWarning, event_loop),
+
+      PlatformEvent::DeviceEvent { device_id, event } => {
+        self.dispatch(AppEvent::DeviceEvent { device_id, event }, event_loop);
+      },
+
+      PlatformEvent::UserEvent(PlatformEventExt::User(user_event)) => {
+        self.dispatch(AppEvent::User(user_event), event_loop);
       },
 
-      PlatformEvent::Resumed => {
-        self.app.event(app_ctx, &AppEvent::Resumed);
-        self.after_event(event_loop, None);
+      PlatformEvent::UserEvent(PlatformEventExt::AppWake) => self.wake(event_loop),
+
+      #[cfg(any(feature = "clipboard", all(feature = "web_clipboard", target_family="wasm")))]
+      PlatformEvent::UserEvent(PlatformEventExt::ClipboardFetch { window_id: id }) if self.app_ctx.window_ctx(id).is_some() => {
+        self.dispatch(AppEvent::ClipboardFetch, event_loop);
       },
 
-      PlatformEvent::Suspended => {
-        self.app.event(app_ctx, &AppEvent::Suspended);
-        self.after_event(event_loop, None);
+      #[cfg(all(feature = "web_clipboard", target_family="wasm"))]
+      PlatformEvent::UserEvent(PlatformEventExt::ClipboardPaste { window_id: id }) if self.app_ctx.window_ctx(id).is_some() => {
+        self.dispatch(AppEvent::ClipboardPaste, event_loop);
       },
 
       #[cfg(all(feature = "web_clipboard", target_family="wasm"))]
-      PlatformEvent::UserEvent(user_event) => match user_event {
-        PlatformEventExt::ClipboardFetch { window_id: id } if id == self.window_id => {
-          self.app.event(app_ctx, &AppEvent::ClipboardFetch);
-          self.after_event(event_loop, None);
-        },
-        PlatformEventExt::ClipboardPaste { window_id: id } if id == self.window_id  => {
-          self.app.event(app_ctx, &AppEvent::ClipboardPaste);
-          self.after_event(event_loop, None);
-        },
-        _ => {},
+      PlatformEvent::UserEvent(PlatformEventExt::ClipboardCopy { window_id: id }) if self.app_ctx.window_ctx(id).is_some() => {
+        self.dispatch(AppEvent::ClipboardCopy, event_loop);
       },
 
-      PlatformEvent::WindowEvent { window_id: id, event: window_event } if id == self.window_id => {
+      #[cfg(all(feature = "web_clipboard", target_family="wasm"))]
+      PlatformEvent::UserEvent(PlatformEventExt::ClipboardCut { window_id: id }) if self.app_ctx.window_ctx(id).is_some() => {
+        self.dispatch(AppEvent::ClipboardCut, event_loop);
+      },
 
-        #[cfg(feature = "auto_wake_lock")]
-        let mut focus_change: Option<bool> = None;
+      #[cfg(all(feature = "web_clipboard", target_family="wasm", web_sys_unstable_apis))]
+      PlatformEvent::UserEvent(PlatformEventExt::ClipboardError { window_id: id, error }) if self.app_ctx.window_ctx(id).is_some() => {
+        self.dispatch(AppEvent::ClipboardError(error), event_loop);
+      },
 
-        // before user handler
-        match &window_event {
+      #[cfg(all(feature = "file_drop", target_family="wasm"))]
+      PlatformEvent::UserEvent(PlatformEventExt::FileHoverEntered { window_id, position }) => {
+        self.dispatch(AppEvent::FileHoverEntered { window_id, position }, event_loop);
+      },
 
-          #[cfg(feature = "frame_timer")]
-          WindowEvent::RedrawRequested => {
+      #[cfg(all(feature = "file_drop", target_family="wasm"))]
+      PlatformEvent::UserEvent(PlatformEventExt::FileHoverLeft { window_id }) => {
+        self.dispatch(AppEvent::FileHoverLeft { window_id }, event_loop);
+      },
 
-            let now = Instant::now();
+      #[cfg(all(feature = "file_drop", target_family="wasm"))]
+      PlatformEvent::UserEvent(PlatformEventExt::FilesDropped { window_id, files, position }) => {
+        self.dispatch(AppEvent::FilesDropped { window_id, files, position }, event_loop);
+      },
 
-            app_ctx.request = None;
-            self.requested.set_state(None);
+      PlatformEvent::WindowEvent { window_id, event } => {
+        self.dispatch(AppEvent::WindowEvent { window_id, event }, event_loop);
+      },
 
-            self.last = if self.next > now || (self.next + app_ctx.duration) <= now {
-              now
-            } else {
-              self.next // avoid timer shifts
-            };
+      _ => {}
 
-            self.next = self.last + app_ctx.duration;
+    }
+  }
 
-            if app_ctx.animate { event_loop.set_wait_until(self.next); }
-            else { event_loop.set_wait(); }
-          },
+  pub(super) fn dispatch(&mut self, event: AppEvent<T>, event_loop: &impl EventLoopTarget) {
 
-          WindowEvent::CloseRequested => {
-            app_ctx.exit = true;
-          },
+    // catch up with the fixed updates before drawing
+    #[cfg(feature = "frame_timer")]
+    if let AppEvent::WindowEvent { window_id, event: WindowEvent::RedrawRequested } = &event {
+      self.fixed_updates(*window_id, self.app_ctx.now(), event_loop);
+    }
 
-          WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged {..} => {
-            app_ctx.window().request_redraw();
-          },
+    let app_ctx = &mut self.app_ctx;
 
-          #[cfg(feature = "auto_wake_lock")]
-          WindowEvent::Focused(focus) => { focus_change = Some(*focus) },
+    #[cfg(feature = "auto_wake_lock")]
+    let mut focus_change: Option<bool> = None;
 
-          _ => {},
+    // before user handler
+    if let AppEvent::WindowEvent { window_id, event: window_event } = &event {
+
+      let window_id = *window_id;
+
+      if app_ctx.window_ctx(window_id).is_none() {
+        return; // unknown or closed window
+      }
+
+      match window_event {
+
+        #[cfg(feature = "frame_timer")]
+        WindowEvent::RedrawRequested => {
+          if let (Some(frame_timer), Some(window_ctx)) = (self.frame_timers.get_mut(&window_id), app_ctx.window_ctx_mut(window_id)) {
+            frame_timer.redraw_requested(window_ctx);
+          }
+        },
+
+        WindowEvent::CloseRequested => {
+          app_ctx.set_close_veto(app_ctx.unsaved_changes && window_id == app_ctx.main_window_id());
+        },
+
+        WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged {..} => {
+          if let Some(window_ctx) = app_ctx.window_ctx(window_id) {
+            window_ctx.request_redraw();
+          }
+        },
+
+        #[cfg(feature = "auto_wake_lock")]
+        WindowEvent::Focused(focus) => { focus_change = Some(*focus) },
+
+        _ => {},
+      }
+
+      #[cfg(all(feature = "file_drop", not(target_family="wasm")))]
+      self.file_drop.window_event(window_id, window_event);
+
+      #[cfg(feature = "window_geometry")]
+      if window_id == app_ctx.main_window_id() && matches!(window_event, WindowEvent::Resized(_) | WindowEvent::Moved(_)) {
+        self.geometry.changed(app_ctx);
+      }
+
+      #[cfg(feature = "frame_timer")]
+      if let (Some(frame_timer), Some(window_ctx)) = (self.frame_timers.get_mut(&window_id), app_ctx.window_ctx_mut(window_id)) {
+
+        frame_timer.window_event(window_event);
+
+        // the window may have moved to another monitor
+        if matches!(window_event, WindowEvent::Moved(_) | WindowEvent::ScaleFactorChanged {..}) {
+          frame_timer.update_pacing(window_ctx);
         }
+      }
+    }
 
-        // exec event handler
-        self.app.event(app_ctx, &AppEvent::WindowEvent(window_event));
+    // exec event handler
+    self.app.event(app_ctx, &event);
 
-        self.after_event(event_loop, {
-          #[cfg(feature = "auto_wake_lock")] { focus_change }
-          #[cfg(not(feature = "auto_wake_lock"))] { None }
-        });
-      },
+    // after user handler
+    if let AppEvent::WindowEvent { window_id, event: WindowEvent::CloseRequested } = &event {
+      if !app_ctx.take_close_veto() {
+        app_ctx.close_window(*window_id);
+      }
+    }
 
-      _ => {}
+    self.after_event(event_loop, {
+      #[cfg(feature = "auto_wake_lock")] { focus_change }
+      #[cfg(not(feature = "auto_wake_lock"))] { None }
+    });
+  }
+
+  #[cfg(feature = "frame_timer")]
+  fn fixed_updates(&mut self, window_id: WindowId, now: Instant, event_loop: &impl EventLoopTarget) {
+
+    let due = match (self.frame_timers.get_mut(&window_id), self.app_ctx.window_ctx(window_id)) {
+      (Some(frame_timer), Some(window_ctx)) => frame_timer.due_updates(window_ctx, now),
+      _ => None,
+    };
 
+    if let Some((dt, steps)) = due {
+      for step in steps {
+        if self.app_ctx.exit { break }
+        self.dispatch(AppEvent::Update { window_id, dt, step }, event_loop);
+      }
     }
   }
 
-  fn after_event(&mut self, event_loop: &ActiveEventLoop, focus_change: Option<bool>) {
+  pub(super) fn wake(&mut self, event_loop: &impl EventLoopTarget) {
+
+    #[cfg(feature = "file_dialog")]
+    while !self.app_ctx.exit {
+      let Some((id, result)) = self.app_ctx.pop_closed_dialog() else { break };
+      self.dispatch(AppEvent::FileDialog { id, result }, event_loop);
+    }
+
+    self.app.wake(&mut self.app_ctx);
+    self.after_event(event_loop, None);
+  }
+
+  fn after_event(&mut self, event_loop: &impl EventLoopTarget, focus_change: Option<bool>) {
+
+    // open and close windows
+    loop {
+      let close_requests = self.app_ctx.take_close_requests();
+      let open_requests = self.app_ctx.take_open_requests();
+
+      if self.app_ctx.exit || close_requests.is_empty() && open_requests.is_empty() { break }
+
+      for window_id in close_requests {
+        if self.app_ctx.remove_window(window_id).is_some() {
+          #[cfg(feature = "frame_timer")] self.frame_timers.remove(&window_id);
+          #[cfg(feature = "file_drop")] self.file_drop.window_closed(window_id);
+          self.app.event(&mut self.app_ctx, &AppEvent::WindowClosed { window_id });
+        }
+      }
+
+      for (request, window_attributes) in open_requests {
+        let window_id = self.app_ctx.insert_window(event_loop.create_window(window_attributes));
+        #[cfg(feature = "recorder")] if let Some(replay) = self.app_ctx.replay_mut() {
+          replay.window_opened(window_id);
+        }
+        #[cfg(feature = "file_drop")] self.file_drop.window_opened(&self.app_ctx, window_id);
+        #[cfg(feature = "frame_timer")] if let Some(window_ctx) = self.app_ctx.window_ctx(window_id) {
+          self.frame_timers.insert(window_id, FrameTimer::new(window_ctx, self.app_ctx.clock().clone()));
+        }
+        self.app.event(&mut self.app_ctx, &AppEvent::WindowOpened { request, window_id });
+      }
+    }
+
+    #[cfg(feature = "window_geometry")]
+    if self.app_ctx.exit {
+      self.geometry.save(&mut self.app_ctx);
+    }
+
+    // persist and notify setting changes
+    #[cfg(feature = "settings")] {
+      if self.app_ctx.exit { self.settings_saver.save(&mut self.app_ctx) }
+      else { self.settings_saver.after_event(&self.app_ctx) }
+
+      let keys = self.app_ctx.settings.take_changes();
+      if !keys.is_empty() && !self.app_ctx.exit {
+        self.app.event(&mut self.app_ctx, &AppEvent::SettingsChanged { keys });
+      }
+    }
 
     let app_ctx = &mut self.app_ctx;
 
@@ -138,6 +354,10 @@ impl<App: AppHandler> AppState<App> {
       return;
     }
 
+    // leaving the page with unsaved changes
+    #[cfg(target_family="wasm")]
+    self.before_unload.set_attached(app_ctx.unsaved_changes);
+
     #[cfg(feature = "auto_wake_lock")]
     fn wake_lock(wake_lock: &mut Option<WakeLock>, action: impl FnOnce(&mut WakeLock) -> anyhow::Result<()>) {
       if let Some(wake_lock) = wake_lock.as_mut() {
@@ -163,83 +383,77 @@ impl<App: AppHandler> AppState<App> {
     }
 
 
+    // throttling of hidden or unfocused windows
+    #[cfg(feature = "frame_timer")] {
+      let policy = self.app_ctx.throttle;
+      let mut throttle_changes = Vec::new();
+
+      for (window_id, frame_timer) in &mut self.frame_timers {
+        if let Some(window_ctx) = self.app_ctx.window_ctx_mut(*window_id) {
+          if let Some(throttle) = frame_timer.update_throttle(window_ctx, &policy) {
+            throttle_changes.push((*window_id, throttle));
+          }
+        }
+      }
+
+      for (window_id, throttle) in throttle_changes {
+        self.app.event(&mut self.app_ctx, &AppEvent::ThrottleChanged { window_id, throttle });
+      }
+    }
+
+    #[cfg(feature = "frame_timer")]
+    let app_ctx = &mut self.app_ctx;
+
     // animation
-    #[cfg(feature = "frame_timer")] // detect state changes ... set control flow
-    {
-      if self.animate.note_change(&app_ctx.animate) {
-        if app_ctx.animate {
+    #[cfg(feature = "frame_timer")] // detect state changes per window, timers set the control flow
+    for (window_id, frame_timer) in &mut self.frame_timers {
+      if let Some(window_ctx) = app_ctx.window_ctx_mut(*window_id) {
 
-          app_ctx.request = None;
-          self.requested.set_state(None);
+        #[cfg_attr(not(feature = "auto_wake_lock"), allow(unused_variables))]
+        let animate_change = frame_timer.update(window_ctx);
 
-          #[cfg(feature = "auto_wake_lock")]
-          if app_ctx.auto_wake_lock {
+        #[cfg(feature = "auto_wake_lock")]
+        match animate_change {
+          Some(true) if app_ctx.auto_wake_lock => {
             // request wake_lock
             wake_lock(&mut self.wake_lock, WakeLock::request);
-          }
+          },
+          Some(false) if !app_ctx.windows().any(|window_ctx| window_ctx.animate) => {
+            // release wake_lock
+            wake_lock(&mut self.wake_lock, WakeLock::release);
+          },
+          _ => {},
+        }
+      }
+    }
+  }
 
-          let now = Instant::now();
+  fn update_control_flow(&self, event_loop: &impl EventLoopTarget) {
 
-          if self.next <= now {
-            self.next = now; // reset frame_timer
-            app_ctx.window().request_redraw();
-          }
-          else {
-            event_loop.set_earlier(self.next);
-          }
-        }
-        else {
-          #[cfg(feature = "auto_wake_lock")]
-          // release wake_lock
-          wake_lock(&mut self.wake_lock, WakeLock::release);
+    event_loop.set_wait();
 
-          event_loop.set_wait();
-        }
-      }
+    #[cfg(feature = "frame_timer")]
+    if let Some(deadline) = self.frame_timers.values().filter_map(FrameTimer::deadline).min() {
+      event_loop.set_wait_until(deadline);
+    }
 
-      if self.requested.changed(&app_ctx.request) {
+    if let Some(deadline) = self.app_ctx.next_timer() {
+      event_loop.set_earlier(deadline);
+    }
 
-        if app_ctx.animate {
-          app_ctx.request = None;
-          self.requested.set_state(None);
-        }
-        else if let Some(delay) = app_ctx.request {
-
-          let earlier = match self.requested.state() {
-            None => {
-              self.requested.set_state(Some(delay));
-              true // is definitely eralier
-            },
-            Some(later) if delay < *later => {
-              self.requested.set_state(Some(delay));
-              true // is earlier, checked
-            },
-            Some(previous) => {
-              app_ctx.request = Some(*previous); // reset to previous
-              false // is not earlier
-            },
-          };
-
-          if earlier {
-            if let Some(instant) = self.last.checked_add(delay) {
-
-              let now = Instant::now();
-              if self.next < now { self.next = now }
-
-              if instant > self.next {
-                event_loop.set_earlier(instant);
-              }
-              else {
-                event_loop.set_earlier(self.next);
-              }
-            }
-            // else consider as infinite delay, keep ControlFLow::Wait
-          }
-        }
-        else {
-          app_ctx.request = *self.requested.state(); // reset to previous
-        }
-      }
+    #[cfg(feature = "recorder")]
+    if let Some(deadline) = self.app_ctx.replay_deadline() {
+      event_loop.set_earlier(deadline);
+    }
+
+    #[cfg(feature = "window_geometry")]
+    if let Some(deadline) = self.geometry.deadline() {
+      event_loop.set_earlier(deadline);
+    }
+
+    #[cfg(feature = "settings")]
+    if let Some(deadline) = self.settings_saver.deadline() {
+      event_loop.set_earlier(deadline);
     }
   }
-}
\ No newline at end of file
+}
@@ -1,17 +1,272 @@
 
 use winit::window::WindowId;
-use std::{rc::Rc, cell::RefCell};
+use std::{rc::Rc, cell::RefCell, future::Future};
 use crate::{*};
-use web_sys::{Clipboard as DomClipboard, ClipboardEvent};
-use js_sys::Function;
+#[cfg(feature = "clipboard_image")]
+use anyhow::{Result as Res, bail};
+use web_sys::{Clipboard as DomClipboard, ClipboardEvent, ClipboardItem as DomClipboardItem, Blob, BlobPropertyBag, File, DomException, PermissionStatus, PermissionState};
+use js_sys::{Function, Array, Object, Uint8Array, Reflect, Promise};
 use wasm_bindgen_futures::JsFuture;
-use wasm_bindgen::{JsValue, closure::Closure};
+use wasm_bindgen::{JsValue, JsCast, closure::Closure};
+
+
+// one representation of the clipboard content, text types hold utf-8
+// pasted files keep their name, images are decoded to rgba if requested with set_decode_images
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClipboardItem {
+  pub mime: String,
+  pub data: Vec<u8>,
+  pub file_name: Option<String>,
+  pub image: Option<RgbaImage>,
+}
+
+impl ClipboardItem {
+
+  pub const TEXT: &'static str = "text/plain";
+  pub const HTML: &'static str = "text/html";
+  pub const PNG: &'static str = "image/png";
+
+  pub fn new(mime: impl Into<String>, data: Vec<u8>) -> Self {
+    Self { mime: mime.into(), data, file_name: None, image: None }
+  }
+
+  pub fn text(mime: impl Into<String>, text: String) -> Self {
+    Self::new(mime, text.into_bytes())
+  }
+
+  #[cfg(feature = "clipboard_image")]
+  pub fn png(image: &RgbaImage) -> Res<Self> {
+    Ok(Self { image: Some(image.clone()), ..Self::new(Self::PNG, image.encode_png()?) })
+  }
+
+  pub fn as_text(&self) -> Option<&str> {
+    std::str::from_utf8(&self.data).ok().filter(|_| self.mime.starts_with("text/"))
+  }
+
+  pub fn is_file(&self) -> bool {
+    self.file_name.is_some()
+  }
+}
+
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RgbaImage {
+  pub width: u32,
+  pub height: u32,
+  pub data: Vec<u8>,
+}
+
+#[cfg(feature = "clipboard_image")]
+impl RgbaImage {
+
+  // any png color type and bit depth, converted to 8 bit rgba
+  pub fn decode_png(bytes: &[u8]) -> Res<Self> {
+
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+    buf.truncate(info.buffer_size());
+
+    let data = match info.color_type {
+      png::ColorType::Rgba => buf,
+      png::ColorType::Rgb => buf.chunks_exact(3).flat_map(|px| [px[0], px[1], px[2], 255]).collect(),
+      png::ColorType::GrayscaleAlpha => buf.chunks_exact(2).flat_map(|px| [px[0], px[0], px[0], px[1]]).collect(),
+      png::ColorType::Grayscale => buf.iter().flat_map(|&px| [px, px, px, 255]).collect(),
+      png::ColorType::Indexed => bail!("indexed png wasn't expanded"),
+    };
+
+    Ok(Self { width: info.width, height: info.height, data })
+  }
+
+  pub fn encode_png(&self) -> Res<Vec<u8>> {
+
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, self.width, self.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&self.data)?;
+    writer.finish()?;
+
+    Ok(bytes)
+  }
+}
+
+
+// errors, kept for take_error and sent as AppEvent::ClipboardError by connected clipboards
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClipboardOp { Fetch, FetchItems, Write, WriteItems, Copy }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClipboardErrorKind {
+  NotAvailable, // no navigator.clipboard or the method is missing
+  NotAllowed, // permission denied or no user activation
+  Failed,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClipboardError {
+  pub op: ClipboardOp,
+  pub kind: ClipboardErrorKind,
+  pub message: String,
+}
+
+impl ClipboardError {
+
+  fn not_available(op: ClipboardOp) -> Self {
+    Self { op, kind: ClipboardErrorKind::NotAvailable, message: "clipboard is not available".to_string() }
+  }
+
+  fn from_js(op: ClipboardOp, err: JsValue) -> Self {
+    match err.dyn_ref::<DomException>() {
+      Some(exception) => Self {
+        op,
+        kind: match exception.name().as_str() {
+          "NotAllowedError" | "SecurityError" => ClipboardErrorKind::NotAllowed,
+          _ => ClipboardErrorKind::Failed,
+        },
+        message: exception.message(),
+      },
+      None => Self { op, kind: ClipboardErrorKind::Failed, message: format!("{err:?}") },
+    }
+  }
+}
+
+impl std::fmt::Display for ClipboardError {
+  fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(fmt, "clipboard {:?} failed ({:?}): {}", self.op, self.kind, self.message)
+  }
+}
+
+impl std::error::Error for ClipboardError {}
+
+
+// permission state from navigator.permissions
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClipboardAccess { Read, Write }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClipboardPermission {
+  Granted,
+  Denied,
+  Prompt,
+  Unknown, // the browser doesn't support the query, e.g. firefox for clipboard-read
+}
+
+
+// helper
+
+type EventProxy<T> = Option<(PlatformEventLoopProxy<T>, WindowId)>;
+
+fn send_event<T: 'static>(event_proxy: &EventProxy<T>, event: impl FnOnce(WindowId) -> PlatformEventExt<T>) {
+  if let Some((event_loop_proxy, window_id)) = event_proxy {
+    if event_loop_proxy.send_event(event(*window_id)).is_err() {
+      log_err!("couldn't send clipboard event, event loop closed");
+    }
+  }
+}
+
+fn report_error<T: 'static>(content: &RefCell<Content>, event_proxy: &EventProxy<T>, error: ClipboardError) {
+  log_warn!(error.to_string());
+  content.borrow_mut().error = Some(error.clone());
+  send_event(event_proxy, |window_id| PlatformEventExt::ClipboardError { window_id, error });
+}
+
+// write promises only resolve or reject once the system clipboard is updated
+fn watch_write<T: 'static>(content: &Rc<RefCell<Content>>, event_proxy: &EventProxy<T>, op: ClipboardOp, promise: Promise) {
+
+  let content = Rc::clone(content);
+  let event_proxy = event_proxy.clone();
+
+  wasm_bindgen_futures::spawn_local(async move {
+    if let Err(err) = JsFuture::from(promise).await {
+      report_error(&content, &event_proxy, ClipboardError::from_js(op, err));
+    }
+  });
+}
+
+#[derive(Debug, Default)]
+struct Content {
+  text: Option<String>,
+  items: Vec<ClipboardItem>,
+  selection: Vec<ClipboardItem>,
+  error: Option<ClipboardError>,
+  #[cfg(feature = "clipboard_image")] decode_images: bool,
+}
+
+impl Content {
+
+  fn set_items(&mut self, items: Vec<ClipboardItem>) {
+
+    #[cfg(feature = "clipboard_image")]
+    let mut items = items;
+
+    #[cfg(feature = "clipboard_image")]
+    if self.decode_images {
+      for item in items.iter_mut().filter(|item| item.mime == ClipboardItem::PNG && item.image.is_none()) {
+        item.image = RgbaImage::decode_png(&item.data).map_err(|err| log_warn!(err)).ok();
+      }
+    }
+
+    self.text = items.iter().find(|item| item.mime == ClipboardItem::TEXT && !item.is_file())
+      .and_then(|item| item.as_text()).map(str::to_string);
+    self.items = items;
+  }
+}
+
+async fn blob_bytes(blob: &Blob) -> Result<Vec<u8>, JsValue> {
+  let buffer = JsFuture::from(blob.array_buffer()).await?;
+  Ok(Uint8Array::new(&buffer).to_vec())
+}
+
+// every type of every item from navigator.clipboard.read
+async fn read_items(clipboard: DomClipboard) -> Result<Vec<ClipboardItem>, JsValue> {
+
+  let dom_items: Array = JsFuture::from(clipboard.read()).await?.dyn_into()?;
+  let mut items = Vec::new();
+
+  for dom_item in dom_items.iter() {
+    let dom_item: DomClipboardItem = dom_item.dyn_into()?;
+
+    for mime in dom_item.types().iter().filter_map(|mime| mime.as_string()) {
+      let blob: Blob = JsFuture::from(dom_item.get_type(&mime)).await?.dyn_into()?;
+      items.push(ClipboardItem::new(mime, blob_bytes(&blob).await?));
+    }
+  }
+
+  Ok(items)
+}
+
+// files can't be written to the clipboard, they are skipped
+fn to_dom_item(items: &[ClipboardItem]) -> Result<DomClipboardItem, JsValue> {
+
+  let record = Object::new();
+
+  for item in items.iter().filter(|item| !item.is_file()) {
+    let options = BlobPropertyBag::new();
+    options.set_type(&item.mime);
+
+    let parts = Array::of1(&Uint8Array::from(item.data.as_slice()));
+    let blob = Blob::new_with_u8_array_sequence_and_options(&parts, &options)?;
+
+    Reflect::set(&record, &JsValue::from(item.mime.as_str()), &blob)?;
+  }
+
+  DomClipboardItem::new_with_record_from_str_to_blob_promise(&record)
+}
 
 
 // helper
 
 #[derive(Debug)]
-struct ClipboardHandle { clipboard: DomClipboard, readable: bool, writeable: bool }
+struct ClipboardHandle { clipboard: DomClipboard, readable: bool, writeable: bool, items_readable: bool, items_writeable: bool }
 
 impl ClipboardHandle {
   fn new() -> Option<Self> {
@@ -24,51 +279,110 @@ impl ClipboardHandle {
       let readable = JsValue::from("readText").js_in(&clipboard);
       let writeable = JsValue::from("writeText").js_in(&clipboard);
 
-      Ok(Self { clipboard, readable, writeable })
+      let items_supported = web_sys::window().is_some_and(|win| JsValue::from("ClipboardItem").js_in(&win));
+      let items_readable = items_supported && JsValue::from("read").js_in(&clipboard);
+      let items_writeable = items_supported && JsValue::from("write").js_in(&clipboard);
+
+      Ok(Self { clipboard, readable, writeable, items_readable, items_writeable })
     })
     ().map_err(|err: &str| log_warn!(err)).ok()
   }
 }
 
 
-struct PasteListener { listener: Function }
+// paste, copy and cut listeners on the document
+
+struct ClipboardListener { event_type: &'static str, listener: Function }
+
+impl ClipboardListener {
 
-impl PasteListener {
+  fn paste<T: 'static>(content: Rc<RefCell<Content>>, event_proxy: EventProxy<T>) -> Self {
 
-  fn new(clipboard_content: Rc<RefCell<Option<String>>>, event_proxy: Option<(PlatformEventLoopProxy, WindowId)>) -> Self {
+    let closure: Box<dyn Fn(ClipboardEvent)> = Box::new(move |evt| {
+
+      let Some(transfer) = evt.clipboard_data() else { return };
+
+      // strings are available right away, files are read asynchronously
+      let mut items: Vec<ClipboardItem> = transfer.types().iter()
+        .filter_map(|mime| mime.as_string())
+        .filter(|mime| mime != "Files")
+        .filter_map(|mime| {
+          let text = transfer.get_data(&mime).map_err(|err| log_err!(err)).ok()?;
+          Some(ClipboardItem::text(mime, text))
+        })
+        .collect()
+      ;
 
-    let closure: Box<dyn Fn(ClipboardEvent)> = if let Some((event_loop_proxy, window_id)) = event_proxy {
-      Box::new(move |evt| {
-        if let Some(transfer) = evt.clipboard_data() {
-          clipboard_content.replace(
-            transfer.get_data("text")
-            .map_err(|err| log_err!(err)).ok()
-          );
+      let files: Vec<File> = transfer.files()
+        .map(|files| (0..files.length()).filter_map(|i| files.get(i)).collect())
+        .unwrap_or_default()
+      ;
+
+      content.borrow_mut().text = transfer.get_data("text").map_err(|err| log_err!(err)).ok();
+
+      let content = Rc::clone(&content);
+      let event_proxy = event_proxy.clone();
+
+      wasm_bindgen_futures::spawn_local(async move {
+
+        for file in files {
+          match blob_bytes(&file).await {
+            Ok(data) => items.push(ClipboardItem { file_name: Some(file.name()), ..ClipboardItem::new(file.type_(), data) }),
+            Err(err) => log_err!(err),
+          }
         }
-        if let Err(err) = event_loop_proxy.send_event(PlatformEventExt::ClipboardPaste { window_id }) {
-          log_err!(err);
+
+        content.borrow_mut().set_items(items);
+        send_event(&event_proxy, |window_id| PlatformEventExt::ClipboardPaste { window_id });
+      });
+    });
+
+    Self { event_type: "paste", listener: Closure::wrap(closure).into_js_value().into() }
+  }
+
+  // browsers reliably allow clipboard writes only during copy and cut, so the selection is written synchronously
+  // text goes into the event, other types through navigator.clipboard.write while the user activation lasts
+  fn copy<T: 'static>(
+    cut: bool, content: Rc<RefCell<Content>>, clipboard: Option<DomClipboard>, event_proxy: EventProxy<T>,
+  ) -> Self {
+
+    let closure: Box<dyn Fn(ClipboardEvent)> = Box::new(move |evt| {
+
+      let selection = content.borrow().selection.clone();
+
+      if let (false, Some(transfer)) = (selection.is_empty(), evt.clipboard_data()) {
+
+        for item in &selection {
+          if let Some(text) = item.as_text() {
+            transfer.set_data(&item.mime, text).unwrap_or_else(|err| log_err!(err));
+          }
         }
-      })
-    }
-    else {
-      Box::new(move |evt| {
-        if let Some(transfer) = evt.clipboard_data() {
-          clipboard_content.replace(
-            transfer.get_data("text")
-            .map_err(|err| log_err!(err)).ok()
-          );
+
+        if let Some(clipboard) = clipboard.as_ref().filter(|_| selection.iter().any(|item| item.as_text().is_none())) {
+          match to_dom_item(&selection) {
+            Ok(dom_item) => watch_write(&content, &event_proxy, ClipboardOp::Copy, clipboard.write(&Array::of1(&dom_item))),
+            Err(err) => report_error(&content, &event_proxy, ClipboardError::from_js(ClipboardOp::Copy, err)),
+          }
         }
-      })
-    };
 
-    Self { listener: Closure::wrap(closure).into_js_value().into() }
+        evt.prevent_default();
+        content.borrow_mut().set_items(selection);
+      }
+
+      send_event(&event_proxy, |window_id| match cut {
+        true => PlatformEventExt::ClipboardCut { window_id },
+        false => PlatformEventExt::ClipboardCopy { window_id },
+      });
+    });
+
+    Self { event_type: if cut { "cut" } else { "copy" }, listener: Closure::wrap(closure).into_js_value().into() }
   }
 
   fn attach(&self) -> Result<(), &'static str> {
     let document = web_sys::window().and_then(|win| win.document()).ok_or("couldn't get window.document")?;
 
-    document.add_event_listener_with_callback("paste", &self.listener)
-      .or(Err("couldn't attach PasteListener to document"))?;
+    document.add_event_listener_with_callback(self.event_type, &self.listener)
+      .or(Err("couldn't attach ClipboardListener to document"))?;
 
     Ok(())
   }
@@ -77,115 +391,253 @@ impl PasteListener {
     (|| {
       let document = web_sys::window().and_then(|win| win.document()).ok_or("couldn't get window.document")?;
 
-      document.remove_event_listener_with_callback("paste", &self.listener)
-        .or(Err("couldn't detach PasteListener to document"))?;
+      document.remove_event_listener_with_callback(self.event_type, &self.listener)
+        .or(Err("couldn't detach ClipboardListener from document"))?;
 
       Ok(())
     })
     ().unwrap_or_else(|err: &str| log_err!(err));
   }
 
-  fn attached(clipboard_content: Rc<RefCell<Option<String>>>, event_proxy: Option<(PlatformEventLoopProxy, WindowId)>) -> Option<Self> {
-    let listener = Self::new(clipboard_content, event_proxy);
-    match listener.attach() {
-      Ok(()) => Some(listener),
-      Err(err) => {
-        log_err!(err);
-        None
-      }
-    }
+  fn attach_all<T: 'static>(
+    content: &Rc<RefCell<Content>>, handle: &Option<ClipboardHandle>, event_proxy: EventProxy<T>,
+  ) -> Vec<Self> {
+
+    let clipboard = match handle {
+      Some(ClipboardHandle {items_writeable: true, clipboard, ..}) => Some(clipboard.clone()),
+      _ => None,
+    };
+
+    [
+      Self::paste(Rc::clone(content), event_proxy.clone()),
+      Self::copy(false, Rc::clone(content), clipboard.clone(), event_proxy.clone()),
+      Self::copy(true, Rc::clone(content), clipboard, event_proxy),
+    ]
+    .into_iter()
+    .filter(|listener| listener.attach().map_err(|err| log_err!(err)).is_ok())
+    .collect()
   }
 }
 
 
 // main clipboard
 
-pub struct WebClipboard {
-  content: Rc<RefCell<Option<String>>>,
+pub struct WebClipboard<T: 'static = ()> {
+  content: Rc<RefCell<Content>>,
   handle: Option<ClipboardHandle>,
-  paste_listener: Option<PasteListener>,
-  event_proxy: Option<(PlatformEventLoopProxy, WindowId)>,
+  listeners: Vec<ClipboardListener>,
+  event_proxy: EventProxy<T>,
 }
 
 impl WebClipboard {
 
-  pub fn connect(app_ctx: &AppCtx, attach_listener: bool) -> Self {
+  // clipboard-read or clipboard-write, awaitable under spawn_local
+  pub async fn query_permission(access: ClipboardAccess) -> ClipboardPermission {
 
-    let window_id = app_ctx.window().id();
-    let event_loop_proxy = app_ctx.event_loop_proxy().clone();
+    let name = match access {
+      ClipboardAccess::Read => "clipboard-read",
+      ClipboardAccess::Write => "clipboard-write",
+    };
 
-    let content = RefCell::new(None).into();
+    let query = || -> Result<Promise, JsValue> {
+      let permissions = web_sys::window().ok_or("no window")?.navigator().permissions()?;
+      let descriptor = Object::new();
+      Reflect::set(&descriptor, &JsValue::from("name"), &JsValue::from(name))?;
+      permissions.query(&descriptor)
+    };
 
-    let paste_listener = match attach_listener {
-      true => PasteListener::attached(Rc::clone(&content), Some((event_loop_proxy.clone(), window_id))),
-      false => None,
+    let status = match query() {
+      Ok(promise) => JsFuture::from(promise).await,
+      Err(err) => Err(err),
     };
 
-    Self {
-      content, handle: ClipboardHandle::new(), paste_listener,
-      event_proxy: Some((event_loop_proxy, window_id)),
+    match status.and_then(|status| status.dyn_into::<PermissionStatus>()).map(|status| status.state()) {
+      Ok(PermissionState::Granted) => ClipboardPermission::Granted,
+      Ok(PermissionState::Denied) => ClipboardPermission::Denied,
+      Ok(PermissionState::Prompt) => ClipboardPermission::Prompt,
+      Ok(_) => ClipboardPermission::Unknown,
+      Err(err) => { log_warn!(err); ClipboardPermission::Unknown },
     }
   }
 
   pub fn unconnected(attach_listener: bool) -> Self {
 
-    let content = RefCell::new(None).into();
+    let content = Rc::new(RefCell::new(Content::default()));
+    let handle = ClipboardHandle::new();
 
-    let paste_listener = match attach_listener {
-      true => PasteListener::attached(Rc::clone(&content), None),
-      false => None,
+    let listeners = match attach_listener {
+      true => ClipboardListener::attach_all::<()>(&content, &handle, None),
+      false => Vec::new(),
     };
 
-    Self {
-      content, handle: ClipboardHandle::new(), paste_listener,
-      event_proxy: None,
-    }
+    Self { content, handle, listeners, event_proxy: None }
   }
+}
 
-  pub fn fetch(&self) { // fetches content from system clipboard asynchronously
-    if let Some(ClipboardHandle {readable: true, clipboard, ..}) = &self.handle {
+impl<T: 'static> WebClipboard<T> {
 
-      let content = Rc::clone(&self.content);
-      let promise = clipboard.read_text();
+  pub fn connect(app_ctx: &AppCtx<T>, attach_listener: bool) -> Self {
 
-      if let Some((event_loop_proxy, window_id)) = &self.event_proxy {
-        let event_loop_proxy = event_loop_proxy.clone();
-        let window_id = *window_id;
+    let window_id = app_ctx.window().id();
+    let event_loop_proxy = app_ctx.event_loop_proxy().clone();
 
-        wasm_bindgen_futures::spawn_local(async move {
-          content.replace(
-            match JsFuture::from(promise).await {
-              Ok(res) => res.as_string(),
-              Err(err) => { log_err!(err); None },
-            }
-          );
-          if let Err(err) = event_loop_proxy.send_event(PlatformEventExt::ClipboardFetch { window_id }) {
-            log_err!(err);
-          }
-        });
-      }
-      else {
-        wasm_bindgen_futures::spawn_local(async move {
-          content.replace(
-            match JsFuture::from(promise).await {
-              Ok(res) => res.as_string(),
-              Err(err) => { log_err!(err); None },
-            }
-          );
-        });
+    let content = Rc::new(RefCell::new(Content::default()));
+    let handle = ClipboardHandle::new();
+
+    let listeners = match attach_listener {
+      true => ClipboardListener::attach_all(&content, &handle, Some((event_loop_proxy.clone(), window_id))),
+      false => Vec::new(),
+    };
+
+    Self { content, handle, listeners, event_proxy: Some((event_loop_proxy, window_id)) }
+  }
+
+  // fetches content from system clipboard asynchronously, followed by ClipboardFetch or ClipboardError
+  pub fn fetch(&self) {
+
+    let Some(ClipboardHandle {readable: true, clipboard, ..}) = &self.handle else {
+      return report_error(&self.content, &self.event_proxy, ClipboardError::not_available(ClipboardOp::Fetch));
+    };
+
+    let promise = clipboard.read_text();
+    let content = Rc::clone(&self.content);
+    let event_proxy = self.event_proxy.clone();
+
+    wasm_bindgen_futures::spawn_local(async move {
+      match JsFuture::from(promise).await {
+        Ok(res) => {
+          content.borrow_mut().text = res.as_string();
+          send_event(&event_proxy, |window_id| PlatformEventExt::ClipboardFetch { window_id });
+        },
+        Err(err) => report_error(&content, &event_proxy, ClipboardError::from_js(ClipboardOp::Fetch, err)),
       }
-    }
+    });
   }
 
   pub fn read(&self) -> Option<String> {
-    self.content.borrow().as_ref().cloned()
+    self.content.borrow().text.clone()
   }
 
+  // the content is kept even if the system clipboard can't be written
   pub fn write(&mut self, text: String) {
-    if let Some(ClipboardHandle {writeable: true, clipboard, ..}) = &self.handle {
-      let _promise = clipboard.write_text(&text);
+    match &self.handle {
+      Some(ClipboardHandle {writeable: true, clipboard, ..}) => {
+        watch_write(&self.content, &self.event_proxy, ClipboardOp::Write, clipboard.write_text(&text));
+      },
+      _ => report_error(&self.content, &self.event_proxy, ClipboardError::not_available(ClipboardOp::Write)),
+    }
+    let mut content = self.content.borrow_mut();
+    content.items = vec![ClipboardItem::text(ClipboardItem::TEXT, text.clone())];
+    content.text = Some(text);
+  }
+
+
+  // awaitable variants for code running under spawn_local, the result is returned instead of sent as an event
+  // the futures don't borrow the clipboard
+
+  pub fn read_text(&self) -> impl Future<Output=Result<String, ClipboardError>> + 'static {
+
+    let content = Rc::clone(&self.content);
+
+    let promise = match &self.handle {
+      Some(ClipboardHandle {readable: true, clipboard, ..}) => Ok(clipboard.read_text()),
+      _ => Err(ClipboardError::not_available(ClipboardOp::Fetch)),
+    };
+
+    async move {
+      let res = JsFuture::from(promise?).await.map_err(|err| ClipboardError::from_js(ClipboardOp::Fetch, err))?;
+      let text = res.as_string().unwrap_or_default();
+      content.borrow_mut().text = Some(text.clone());
+      Ok(text)
+    }
+  }
+
+  pub fn write_text(&self, text: String) -> impl Future<Output=Result<(), ClipboardError>> + 'static {
+
+    let promise = match &self.handle {
+      Some(ClipboardHandle {writeable: true, clipboard, ..}) => Ok(clipboard.write_text(&text)),
+      _ => Err(ClipboardError::not_available(ClipboardOp::Write)),
+    };
+
+    let mut content = self.content.borrow_mut();
+    content.items = vec![ClipboardItem::text(ClipboardItem::TEXT, text.clone())];
+    content.text = Some(text);
+
+    async move {
+      JsFuture::from(promise?).await.map_err(|err| ClipboardError::from_js(ClipboardOp::Write, err))?;
+      Ok(())
+    }
+  }
+
+
+  // mime typed content, from fetch_items or the last paste
+
+  pub fn fetch_items(&self) { // like fetch, for all types
+
+    let Some(ClipboardHandle {items_readable: true, clipboard, ..}) = &self.handle else {
+      return report_error(&self.content, &self.event_proxy, ClipboardError::not_available(ClipboardOp::FetchItems));
+    };
+
+    let content = Rc::clone(&self.content);
+    let clipboard = clipboard.clone();
+    let event_proxy = self.event_proxy.clone();
+
+    wasm_bindgen_futures::spawn_local(async move {
+      match read_items(clipboard).await {
+        Ok(items) => {
+          content.borrow_mut().set_items(items);
+          send_event(&event_proxy, |window_id| PlatformEventExt::ClipboardFetch { window_id });
+        },
+        Err(err) => report_error(&content, &event_proxy, ClipboardError::from_js(ClipboardOp::FetchItems, err)),
+      }
+    });
+  }
+
+  pub fn read_items(&self) -> Vec<ClipboardItem> {
+    self.content.borrow().items.clone()
+  }
+
+  pub fn read_item(&self, mime: &str) -> Option<ClipboardItem> {
+    self.content.borrow().items.iter().find(|item| item.mime == mime).cloned()
+  }
+
+  pub fn write_items(&mut self, items: Vec<ClipboardItem>) {
+    match (&self.handle, to_dom_item(&items)) {
+      (Some(ClipboardHandle {items_writeable: true, clipboard, ..}), Ok(dom_item)) => {
+        watch_write(&self.content, &self.event_proxy, ClipboardOp::WriteItems, clipboard.write(&Array::of1(&dom_item)));
+      },
+      (Some(ClipboardHandle {items_writeable: true, ..}), Err(err)) => {
+        report_error(&self.content, &self.event_proxy, ClipboardError::from_js(ClipboardOp::WriteItems, err));
+      },
+      _ => report_error(&self.content, &self.event_proxy, ClipboardError::not_available(ClipboardOp::WriteItems)),
     }
-    self.content.replace(Some(text));
+    self.content.borrow_mut().set_items(items);
+  }
+
+  // the last error, also for unconnected clipboards
+  pub fn take_error(&self) -> Option<ClipboardError> {
+    self.content.borrow_mut().error.take()
+  }
+
+  // what the next copy or cut writes, followed by AppEvent::ClipboardCopy or ClipboardCut
+  // after a cut, remove the selected content and update or clear the selection
+
+  pub fn set_selection(&mut self, items: Vec<ClipboardItem>) {
+    self.content.borrow_mut().selection = items;
+  }
+
+  pub fn set_selection_text(&mut self, text: String) {
+    self.set_selection(vec![ClipboardItem::text(ClipboardItem::TEXT, text)]);
+  }
+
+  pub fn clear_selection(&mut self) {
+    self.content.borrow_mut().selection.clear();
+  }
+
+  // decode pasted and fetched png images into ClipboardItem::image
+  #[cfg(feature = "clipboard_image")]
+  pub fn set_decode_images(&mut self, decode_images: bool) {
+    self.content.borrow_mut().decode_images = decode_images;
   }
 
 
@@ -196,7 +648,7 @@ impl WebClipboard {
   }
 
   pub fn is_listening(&self) -> bool {
-    self.paste_listener.is_some()
+    !self.listeners.is_empty()
   }
 
   pub fn is_readable(&self) -> bool {
@@ -207,12 +659,20 @@ impl WebClipboard {
     matches!(self.handle, Some(ClipboardHandle {writeable: true, ..}))
   }
 
+  pub fn are_items_readable(&self) -> bool {
+    matches!(self.handle, Some(ClipboardHandle {items_readable: true, ..}))
+  }
+
+  pub fn are_items_writeable(&self) -> bool {
+    matches!(self.handle, Some(ClipboardHandle {items_writeable: true, ..}))
+  }
+
 }
 
 
-impl Drop for WebClipboard {
+impl<T: 'static> Drop for WebClipboard<T> {
   fn drop(&mut self) {
-    if let Some(listener) = &self.paste_listener {
+    for listener in &self.listeners {
       listener.detach()
     }
   }
@@ -220,7 +680,7 @@ impl Drop for WebClipboard {
 
 use std::fmt;
 
-impl fmt::Debug for WebClipboard {
+impl<T: 'static> fmt::Debug for WebClipboard<T> {
   fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
     fmt.debug_struct("WebClipboard")
     .field("connected", &self.is_connected())
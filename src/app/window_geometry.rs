@@ -0,0 +1,157 @@
+
+use serde::{Serialize, Deserialize};
+use winit::{
+  window::{Window, WindowAttributes, Fullscreen}, event_loop::ActiveEventLoop,
+  dpi::{PhysicalSize, PhysicalPosition}, monitor::MonitorHandle,
+};
+use crate::{*, time::{Instant, Duration}};
+use super::AppCtx;
+
+
+// main window geometry, stored in the settings
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+  pub size: PhysicalSize<u32>,
+  pub position: Option<PhysicalPosition<i32>>,
+  pub maximized: bool,
+  pub fullscreen: bool,
+  pub monitor: Option<String>,
+}
+
+impl WindowGeometry {
+
+  pub const SETTINGS_KEY: &'static str = "window_geometry";
+
+  pub fn of_window(window: &Window) -> Self {
+    Self {
+      size: window.inner_size(),
+      position: window.outer_position().ok(),
+      maximized: window.is_maximized(),
+      fullscreen: window.fullscreen().is_some(),
+      monitor: window.current_monitor().and_then(|monitor| monitor.name()),
+    }
+  }
+
+  // position and exclusive placement only if the saved monitor still exists and the window stays reachable on it
+  // a zero size keeps the size of the window attributes
+  pub fn restore(&self, event_loop: &ActiveEventLoop, window_attributes: WindowAttributes) -> WindowAttributes {
+
+    let monitor = self.monitor.as_ref().and_then(|name| {
+      event_loop.available_monitors().find(|monitor| monitor.name().as_ref() == Some(name))
+    });
+
+    let fallback = monitor.clone().or_else(|| event_loop.primary_monitor());
+
+    // not larger than the monitor
+    let size = match fallback.as_ref().map(MonitorHandle::size) {
+      Some(bounds) if bounds.width > 0 && bounds.height > 0 => PhysicalSize::new(self.size.width.min(bounds.width), self.size.height.min(bounds.height)),
+      _ => self.size,
+    };
+
+    let mut window_attributes = window_attributes.with_maximized(self.maximized);
+
+    if size.width > 0 && size.height > 0 {
+      window_attributes = window_attributes.with_inner_size(size);
+    }
+
+    if let (Some(monitor), Some(position)) = (&monitor, self.position) {
+      if is_reachable(monitor, position) {
+        window_attributes = window_attributes.with_position(position);
+      }
+    }
+
+    if self.fullscreen {
+      window_attributes = window_attributes.with_fullscreen(Some(Fullscreen::Borderless(monitor)));
+    }
+
+    window_attributes
+  }
+}
+
+// the top left corner, where the title bar usually is, is on the monitor
+fn is_reachable(monitor: &MonitorHandle, position: PhysicalPosition<i32>) -> bool {
+  let (origin, size) = (monitor.position(), monitor.size());
+  let right = origin.x.saturating_add(size.width.min(i32::MAX as u32) as i32);
+  let bottom = origin.y.saturating_add(size.height.min(i32::MAX as u32) as i32);
+  (origin.x..right).contains(&position.x) && (origin.y..bottom).contains(&position.y)
+}
+
+
+// saves the geometry once moves and resizes settle and on exit, only with persistent settings
+// while maximized or fullscreen the last normal size and position are saved, so leaving it after a restart
+// doesn't give a window as large as the screen, nothing is saved while minimized
+
+const SETTLE_DURATION: Duration = Duration::from_secs(1);
+
+pub(super) struct GeometryTracker {
+  saved: Option<WindowGeometry>,
+  normal: Option<(PhysicalSize<u32>, Option<PhysicalPosition<i32>>)>,
+  deadline: Option<Instant>,
+}
+
+impl GeometryTracker {
+
+  pub(super) fn new<T: 'static>(app_ctx: &AppCtx<T>) -> Self {
+    let saved: Option<WindowGeometry> = app_ctx.settings.get(WindowGeometry::SETTINGS_KEY);
+    let normal = saved.as_ref().filter(|geometry| !is_empty(geometry.size)).map(|geometry| (geometry.size, geometry.position));
+    Self { saved, normal, deadline: None }
+  }
+
+  pub(super) fn deadline(&self) -> Option<Instant> {
+    self.deadline
+  }
+
+  pub(super) fn changed<T: 'static>(&mut self, app_ctx: &AppCtx<T>) {
+    if let Some(window) = main_window(app_ctx) {
+      self.update_normal(window);
+    }
+    self.deadline = Some(app_ctx.now() + SETTLE_DURATION);
+  }
+
+  fn update_normal(&mut self, window: &Window) {
+    let size = window.inner_size();
+    if !window.is_maximized() && window.fullscreen().is_none() && window.is_minimized() != Some(true) && !is_empty(size) {
+      self.normal = Some((size, window.outer_position().ok()));
+    }
+  }
+
+  pub(super) fn check_deadline<T: 'static>(&mut self, app_ctx: &mut AppCtx<T>, now: Instant) {
+    if self.deadline.is_some_and(|deadline| deadline <= now) {
+      self.save(app_ctx);
+    }
+  }
+
+  pub(super) fn save<T: 'static>(&mut self, app_ctx: &mut AppCtx<T>) {
+
+    self.deadline = None;
+
+    let Some(window) = main_window(app_ctx).filter(|_| app_ctx.settings.is_persistent()) else { return };
+
+    if window.is_minimized() == Some(true) || is_empty(window.inner_size()) {
+      return;
+    }
+
+    self.update_normal(window);
+
+    let mut geometry = WindowGeometry::of_window(window);
+
+    if let (true, Some((size, position))) = (geometry.maximized || geometry.fullscreen, self.normal) {
+      (geometry.size, geometry.position) = (size, position);
+    }
+
+    if self.saved.as_ref() != Some(&geometry) {
+      app_ctx.settings.set(WindowGeometry::SETTINGS_KEY, &geometry).unwrap_or_else(|err| log_err!(err));
+      self.saved = Some(geometry);
+    }
+  }
+}
+
+fn main_window<T: 'static>(app_ctx: &AppCtx<T>) -> Option<&Window> {
+  let window_ctx = app_ctx.window_ctx(app_ctx.main_window_id())?;
+  (!window_ctx.is_headless()).then(|| window_ctx.window())
+}
+
+fn is_empty(size: PhysicalSize<u32>) -> bool {
+  size.width == 0 || size.height == 0
+}
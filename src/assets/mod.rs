@@ -0,0 +1,294 @@
+
+use std::{future::Future, sync::{Arc, atomic::{AtomicU64, AtomicUsize, Ordering}}};
+
+#[cfg(not(target_family="wasm"))]
+mod native;
+
+#[cfg(not(target_family="wasm"))]
+use {native::*, std::path::PathBuf};
+
+
+#[cfg(target_family="wasm")]
+mod web;
+
+#[cfg(target_family="wasm")]
+use web::*;
+
+
+// what asset paths are resolved against
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum AssetBase {
+    #[cfg(not(target_family="wasm"))] #[default] ExecutableDir,
+    #[cfg(not(target_family="wasm"))] CurrentDir,
+    #[cfg(not(target_family="wasm"))] Dir(PathBuf),
+    #[cfg(target_family="wasm")] #[default] PageUrl, // document.baseURI
+    #[cfg(target_family="wasm")] Url(String), // end it with / for a directory
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetErrorKind {
+    InvalidPath, // empty, absolute or leaving the base
+    NotFound,
+    NotAllowed, // file permissions, or a 401/403 response
+    Http(u16), // other unsuccessful responses
+    Network, // also blocked cross-origin requests, fetch doesn't tell them apart
+    InvalidUtf8, // only from load_string
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssetError {
+    pub path: String,
+    pub kind: AssetErrorKind,
+    pub message: String,
+}
+
+impl AssetError {
+
+    fn new(path: &str, kind: AssetErrorKind, message: impl Into<String>) -> Self {
+        Self { path: path.to_string(), kind, message: message.into() }
+    }
+}
+
+impl std::fmt::Display for AssetError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(fmt, "couldn't load asset {} ({:?}): {}", self.path, self.kind, self.message)
+    }
+}
+
+impl std::error::Error for AssetError {}
+
+
+// bytes loaded so far, shared by clones and by every load it's passed to, e.g. for a loading screen
+// read it when drawing, total is None while a load of unknown size is running
+
+#[derive(Debug, Clone, Default)]
+pub struct AssetProgress {
+    state: Arc<ProgressState>,
+}
+
+#[derive(Debug, Default)]
+struct ProgressState {
+    loaded: AtomicU64,
+    total: AtomicU64,
+    running: AtomicUsize,
+    unknown_sizes: AtomicUsize,
+}
+
+impl AssetProgress {
+
+    pub fn new() -> Self { Self::default() }
+
+    pub fn loaded(&self) -> u64 {
+        self.state.loaded.load(Ordering::Relaxed)
+    }
+
+    pub fn total(&self) -> Option<u64> {
+        (self.state.unknown_sizes.load(Ordering::Relaxed) == 0).then(|| self.state.total.load(Ordering::Relaxed))
+    }
+
+    // between 0 and 1, 1 if there is nothing to load
+    pub fn fraction(&self) -> Option<f32> {
+        self.total().map(|total| match total {
+            0 => 1.0,
+            total => (self.loaded() as f64 / total as f64).min(1.0) as f32,
+        })
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.state.running.load(Ordering::Relaxed) == 0
+    }
+}
+
+
+// one load's share of the progress, failed and dropped loads count as done
+
+struct LoadTracker {
+    progress: Option<AssetProgress>,
+    loaded: u64,
+    size: Option<u64>,
+}
+
+impl LoadTracker {
+
+    fn new(progress: Option<AssetProgress>) -> Self {
+        if let Some(progress) = &progress {
+            progress.state.running.fetch_add(1, Ordering::Relaxed);
+            progress.state.unknown_sizes.fetch_add(1, Ordering::Relaxed);
+        }
+        Self { progress, loaded: 0, size: None }
+    }
+
+    // once known, None keeps the total unknown until the load ends
+    fn set_size(&mut self, size: Option<u64>) {
+        if let (Some(progress), Some(size)) = (&self.progress, size) {
+            progress.state.total.fetch_add(size, Ordering::Relaxed);
+            progress.state.unknown_sizes.fetch_sub(1, Ordering::Relaxed);
+        }
+        self.size = size;
+    }
+
+    fn advance(&mut self, len: u64) {
+        self.loaded += len;
+        let Some(progress) = &self.progress else { return };
+        progress.state.loaded.fetch_add(len, Ordering::Relaxed);
+
+        // more than announced, the total grows with it
+        if let Some(size) = self.size.filter(|&size| size < self.loaded) {
+            progress.state.total.fetch_add(self.loaded - size, Ordering::Relaxed);
+            self.size = Some(self.loaded);
+        }
+    }
+}
+
+impl Drop for LoadTracker {
+    fn drop(&mut self) {
+        let Some(progress) = &self.progress else { return };
+        match self.size {
+            Some(size) => { progress.state.loaded.fetch_add(size - self.loaded, Ordering::Relaxed); },
+            None => {
+                progress.state.total.fetch_add(self.loaded, Ordering::Relaxed);
+                progress.state.unknown_sizes.fetch_sub(1, Ordering::Relaxed);
+            },
+        }
+        progress.state.running.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+
+// loads app resources from files on native and with fetch on web
+// paths are relative with / separators, the futures don't borrow the loader
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Assets {
+    pub base: AssetBase,
+}
+
+impl Assets {
+
+    pub fn new(base: AssetBase) -> Self {
+        Self { base }
+    }
+
+    #[cfg(not(target_family="wasm"))]
+    pub fn resolve(&self, path: &str) -> Result<PathBuf, AssetError> {
+        check_path(path)?;
+        Ok(base_dir(&self.base).map_err(|err| io_error(path, err))?.join(path))
+    }
+
+    #[cfg(target_family="wasm")]
+    pub fn resolve(&self, path: &str) -> Result<String, AssetError> {
+        check_path(path)?;
+        resolve_url(&self.base, path).map_err(|err| AssetError::new(path, AssetErrorKind::InvalidPath, js_message(&err)))
+    }
+
+    pub fn load_bytes(&self, path: &str) -> impl Future<Output=Result<Vec<u8>, AssetError>> + 'static {
+        self.load(path, None)
+    }
+
+    pub fn load_bytes_with_progress(&self, path: &str, progress: &AssetProgress) -> impl Future<Output=Result<Vec<u8>, AssetError>> + 'static {
+        self.load(path, Some(progress.clone()))
+    }
+
+    pub fn load_string(&self, path: &str) -> impl Future<Output=Result<String, AssetError>> + 'static {
+        let bytes = self.load(path, None);
+        let path = path.to_string();
+        async move { into_string(&path, bytes.await?) }
+    }
+
+    pub fn load_string_with_progress(&self, path: &str, progress: &AssetProgress) -> impl Future<Output=Result<String, AssetError>> + 'static {
+        let bytes = self.load(path, Some(progress.clone()));
+        let path = path.to_string();
+        async move { into_string(&path, bytes.await?) }
+    }
+
+    // counted in the progress right away, not only once polled
+    fn load(&self, path: &str, progress: Option<AssetProgress>) -> impl Future<Output=Result<Vec<u8>, AssetError>> + 'static {
+
+        let tracker = LoadTracker::new(progress);
+        let location = self.resolve(path);
+        let path = path.to_string();
+
+        async move {
+            #[cfg(not(target_family="wasm"))] { read_file(&path, location?, tracker).await }
+            #[cfg(target_family="wasm")] { fetch(&path, location?, tracker).await }
+        }
+    }
+}
+
+
+// with the default base, the executable's directory on native and the page on web
+
+pub fn load_bytes(path: &str) -> impl Future<Output=Result<Vec<u8>, AssetError>> + 'static {
+    Assets::default().load_bytes(path)
+}
+
+pub fn load_string(path: &str) -> impl Future<Output=Result<String, AssetError>> + 'static {
+    Assets::default().load_string(path)
+}
+
+
+// also rejects colons, so drive letters and url schemes can't replace the base
+// and percent-encoded dots, which url parsing treats like .. as well
+fn check_path(path: &str) -> Result<(), AssetError> {
+    let invalid = path.is_empty() || path.starts_with('/') || path.contains(['\\', ':'])
+        || path.split('/').any(|segment| segment.to_ascii_lowercase().replace("%2e", ".") == "..");
+    match invalid {
+        true => Err(AssetError::new(path, AssetErrorKind::InvalidPath, "expected a relative path inside the base")),
+        false => Ok(()),
+    }
+}
+
+fn into_string(path: &str, bytes: Vec<u8>) -> Result<String, AssetError> {
+    String::from_utf8(bytes).map_err(|err| AssetError::new(path, AssetErrorKind::InvalidUtf8, err.to_string()))
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use std::fs;
+    use pollster::block_on;
+    use super::{Assets, AssetBase, AssetError, AssetErrorKind, AssetProgress, check_path};
+
+    fn kind<V>(res: Result<V, AssetError>) -> Option<AssetErrorKind> {
+        res.err().map(|err| err.kind)
+    }
+
+    #[test]
+    fn load_from_dir() {
+
+        let dir = std::env::temp_dir().join(format!("platform-assets-{}", std::process::id()));
+        fs::create_dir_all(dir.join("text")).unwrap();
+        fs::write(dir.join("text/hello.txt"), "hello").unwrap();
+        fs::write(dir.join("data.bin"), [0xff, 0xfe]).unwrap();
+
+        let assets = Assets::new(AssetBase::Dir(dir.clone()));
+        let progress = AssetProgress::new();
+
+        let hello = assets.load_string_with_progress("text/hello.txt", &progress);
+        assert_eq!((progress.total(), progress.is_done()), (None, false));
+        assert_eq!(block_on(hello).unwrap(), "hello");
+        assert_eq!((progress.loaded(), progress.total(), progress.is_done()), (5, Some(5), true));
+
+        assert_eq!(block_on(assets.load_bytes("data.bin")).unwrap(), [0xff, 0xfe]);
+        assert_eq!(kind(block_on(assets.load_string("data.bin"))), Some(AssetErrorKind::InvalidUtf8));
+        assert_eq!(kind(block_on(assets.load_bytes("missing.txt"))), Some(AssetErrorKind::NotFound));
+        assert_eq!(kind(block_on(assets.load_bytes("../hello.txt"))), Some(AssetErrorKind::InvalidPath));
+        assert_eq!(kind(block_on(assets.load_bytes("/etc/hosts"))), Some(AssetErrorKind::InvalidPath));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn checked_paths() {
+        for path in ["data.bin", "text/hello.txt", "./a.txt", "%2e/a.txt", "a..b/c.txt"] {
+            assert!(check_path(path).is_ok(), "{path}");
+        }
+        for path in ["", "/a.txt", "a\\b.txt", "c:/a.txt", "../a.txt", "a/../../b.txt", "%2e%2e/secret.json", ".%2E/a.txt", "%2E./a.txt"] {
+            assert!(check_path(path).is_err(), "{path}");
+        }
+    }
+}
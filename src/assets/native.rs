@@ -0,0 +1,49 @@
+use std::{path::PathBuf, fs::File, io::{self, Read, ErrorKind}, env, future::Future};
+use crate::in_background;
+use super::{AssetBase, AssetError, AssetErrorKind, LoadTracker};
+
+
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+
+pub(super) fn base_dir(base: &AssetBase) -> io::Result<PathBuf> {
+    match base {
+        AssetBase::ExecutableDir => env::current_exe()?.parent().map(PathBuf::from)
+            .ok_or_else(|| io::Error::other("the executable has no parent directory")),
+        AssetBase::CurrentDir => env::current_dir(),
+        AssetBase::Dir(dir) => Ok(dir.clone()),
+    }
+}
+
+pub(super) fn io_error(path: &str, err: io::Error) -> AssetError {
+    let kind = match err.kind() {
+        ErrorKind::NotFound => AssetErrorKind::NotFound,
+        ErrorKind::PermissionDenied => AssetErrorKind::NotAllowed,
+        _ => AssetErrorKind::Failed,
+    };
+    AssetError::new(path, kind, err.to_string())
+}
+
+
+// read in chunks on a background thread, for the progress
+pub(super) fn read_file(path: &str, file_path: PathBuf, mut tracker: LoadTracker) -> impl Future<Output=Result<Vec<u8>, AssetError>> {
+
+    let path = path.to_string();
+
+    in_background(move || {
+        let res = (|| {
+            let mut file = File::open(&file_path)?;
+            let size = file.metadata()?.len();
+            tracker.set_size(Some(size));
+
+            let mut data = Vec::with_capacity(size as usize);
+            loop {
+                match (&mut file).take(CHUNK_SIZE).read_to_end(&mut data)? {
+                    0 => break Ok(data),
+                    len => tracker.advance(len as u64),
+                }
+            }
+        })();
+        res.map_err(|err| io_error(&path, err))
+    })
+}
@@ -0,0 +1,69 @@
+use web_sys::{Response, ReadableStreamDefaultReader, ReadableStreamReadResult, Url};
+use js_sys::Uint8Array;
+use wasm_bindgen::{JsValue, JsCast};
+use wasm_bindgen_futures::JsFuture;
+use super::{AssetBase, AssetError, AssetErrorKind, LoadTracker};
+
+
+fn page_url() -> Result<String, JsValue> {
+    let document = web_sys::window().and_then(|win| win.document()).ok_or("couldn't get window.document")?;
+    document.base_uri()?.ok_or_else(|| "document has no base URI".into())
+}
+
+pub(super) fn resolve_url(base: &AssetBase, path: &str) -> Result<String, JsValue> {
+    let base = match base {
+        AssetBase::PageUrl => page_url()?,
+        AssetBase::Url(url) => url.clone(),
+    };
+    Ok(Url::new_with_base(path, &base)?.href())
+}
+
+pub(super) fn js_message(err: &JsValue) -> String {
+    match err.dyn_ref::<js_sys::Error>() {
+        Some(err) => err.message().into(),
+        None => err.as_string().unwrap_or_else(|| format!("{err:?}")),
+    }
+}
+
+
+// streams the body for the progress
+// the size comes from Content-Length, unless the body is compressed and the length doesn't match the bytes read
+pub(super) async fn fetch(path: &str, url: String, mut tracker: LoadTracker) -> Result<Vec<u8>, AssetError> {
+
+    let error = |kind| move |err: JsValue| AssetError::new(path, kind, js_message(&err));
+
+    let window = web_sys::window().ok_or_else(|| AssetError::new(path, AssetErrorKind::Failed, "couldn't get window"))?;
+    let response: Response = JsFuture::from(window.fetch_with_str(&url)).await
+        .map_err(error(AssetErrorKind::Network))?.unchecked_into();
+
+    if !response.ok() {
+        let kind = match response.status() {
+            404 | 410 => AssetErrorKind::NotFound,
+            401 | 403 => AssetErrorKind::NotAllowed,
+            status => AssetErrorKind::Http(status),
+        };
+        return Err(AssetError::new(path, kind, format!("{} {} {url}", response.status(), response.status_text())));
+    }
+
+    let headers = response.headers();
+    let header = |name| headers.get(name).ok().flatten();
+    let compressed = header("content-encoding").is_some_and(|encoding| encoding != "identity");
+    tracker.set_size(header("content-length").filter(|_| !compressed).and_then(|len| len.parse().ok()));
+
+    let Some(body) = response.body() else { return Ok(Vec::new()) };
+    let reader: ReadableStreamDefaultReader = body.get_reader().unchecked_into();
+
+    let mut data = Vec::new();
+    loop {
+        let chunk: ReadableStreamReadResult = JsFuture::from(reader.read()).await
+            .map_err(error(AssetErrorKind::Network))?.unchecked_into();
+
+        if chunk.get_done().unwrap_or(false) { break Ok(data) }
+
+        let chunk = Uint8Array::new(&chunk.get_value());
+        let start = data.len();
+        data.resize(start + chunk.length() as usize, 0);
+        chunk.copy_to(&mut data[start..]);
+        tracker.advance(chunk.length() as u64);
+    }
+}
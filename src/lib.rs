@@ -30,4 +30,12 @@ pub mod icon_loader;
 
 // wake_lock
 #[cfg(feature = "wake_lock")]
-pub mod wake_lock;
\ No newline at end of file
+pub mod wake_lock;
+
+// settings
+#[cfg(feature = "settings")]
+pub mod settings;
+
+// assets
+#[cfg(feature = "assets")]
+pub mod assets;
\ No newline at end of file
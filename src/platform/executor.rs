@@ -0,0 +1,191 @@
+
+// single threaded executor for local futures on native, driven by the event loop
+
+use std::{future::Future, pin::Pin, cell::{Cell, RefCell}, collections::BTreeMap, mem};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Wake, Waker};
+use crate::time::Instant;
+
+
+type Task = Pin<Box<dyn Future<Output = ()>>>;
+type WakeHook = Box<dyn Fn() + Send>;
+pub(crate) type TimerKey = (Instant, u64);
+
+
+#[derive(Default)]
+struct ReadyQueue {
+    queue: Mutex<Vec<usize>>,
+    wake_hook: Mutex<Option<WakeHook>>,
+}
+
+impl ReadyQueue {
+    fn push(&self, id: usize) {
+        let mut queue = self.queue.lock().unwrap();
+        let was_empty = queue.is_empty();
+        queue.push(id);
+        drop(queue);
+
+        if was_empty {
+            if let Some(wake_hook) = self.wake_hook.lock().unwrap().as_ref() { wake_hook() }
+        }
+    }
+
+    fn take(&self) -> Vec<usize> {
+        mem::take(&mut *self.queue.lock().unwrap())
+    }
+}
+
+
+struct TaskWaker {
+    id: usize,
+    ready: Arc<ReadyQueue>,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) { self.ready.push(self.id) }
+    fn wake_by_ref(self: &Arc<Self>) { self.ready.push(self.id) }
+}
+
+
+#[derive(Default)]
+struct Executor {
+    tasks: RefCell<Vec<Option<Task>>>,
+    free: RefCell<Vec<usize>>,
+    ready: Arc<ReadyQueue>,
+    timers: RefCell<BTreeMap<TimerKey, Waker>>,
+    timer_count: Cell<u64>,
+}
+
+impl Executor {
+
+    fn insert(&self, task: Task) -> usize {
+        if let Some(id) = self.free.borrow_mut().pop() {
+            self.tasks.borrow_mut()[id] = Some(task);
+            id
+        }
+        else {
+            let mut tasks = self.tasks.borrow_mut();
+            tasks.push(Some(task));
+            tasks.len() - 1
+        }
+    }
+
+    fn poll(&self, id: usize) {
+
+        // take the task out, so it may spawn further tasks while polled
+        let Some(mut task) = self.tasks.borrow_mut().get_mut(id).and_then(Option::take) else {
+            return; // finished, or already being polled
+        };
+
+        let waker = Waker::from(Arc::new(TaskWaker { id, ready: Arc::clone(&self.ready) }));
+
+        match task.as_mut().poll(&mut Context::from_waker(&waker)) {
+            Poll::Ready(()) => self.free.borrow_mut().push(id),
+            Poll::Pending => self.tasks.borrow_mut()[id] = Some(task),
+        }
+    }
+}
+
+
+thread_local! {
+    static EXECUTOR: Executor = Executor::default();
+}
+
+
+// polls the task once right away
+pub(crate) fn spawn(task: Task) {
+    EXECUTOR.with(|executor| {
+        let id = executor.insert(task);
+        executor.poll(id);
+    });
+}
+
+// polls the tasks that were woken until now, returns if any were polled
+pub(crate) fn run_ready() -> bool {
+    EXECUTOR.with(|executor| {
+        let ready = executor.ready.take();
+        for &id in &ready { executor.poll(id) }
+        !ready.is_empty()
+    })
+}
+
+// called when the first task gets woken after run_ready, from any thread
+pub(crate) fn set_wake_hook(wake_hook: impl Fn() + Send + 'static) {
+    EXECUTOR.with(|executor| {
+        executor.ready.wake_hook.lock().unwrap().replace(Box::new(wake_hook));
+    });
+}
+
+
+
+// timers, the event loop waits until the next deadline
+
+pub(crate) fn register_timer(key: Option<TimerKey>, deadline: Instant, waker: &Waker) -> TimerKey {
+    EXECUTOR.with(|executor| {
+        let mut timers = executor.timers.borrow_mut();
+
+        if let Some(registered) = key.and_then(|key| timers.get_mut(&key)) {
+            registered.clone_from(waker);
+            return key.unwrap();
+        }
+
+        executor.timer_count.set(executor.timer_count.get() + 1);
+        let key = (deadline, executor.timer_count.get());
+        timers.insert(key, waker.clone());
+        key
+    })
+}
+
+pub(crate) fn cancel_timer(key: TimerKey) {
+    let _res = EXECUTOR.try_with(|executor| executor.timers.borrow_mut().remove(&key));
+}
+
+// wakes the tasks of elapsed timers
+pub(crate) fn fire_timers(now: Instant) {
+    EXECUTOR.with(|executor| {
+        let pending = executor.timers.borrow_mut().split_off(&(now, u64::MAX));
+        let elapsed = executor.timers.replace(pending);
+        for waker in elapsed.into_values() { waker.wake() }
+    });
+}
+
+pub(crate) fn next_timer() -> Option<Instant> {
+    EXECUTOR.with(|executor| executor.timers.borrow().first_key_value().map(|((deadline, _), _)| *deadline))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::{future::Future, pin::Pin, task::{Context, Poll}};
+    use crate::spawn_local;
+    use super::run_ready;
+
+    struct YieldOnce(bool);
+
+    impl Future for YieldOnce {
+        type Output = ();
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 { return Poll::Ready(()) }
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
+    #[test]
+    fn spawn_and_join() {
+
+        let handle = spawn_local(async { YieldOnce(false).await; 5 });
+        let mut joined = spawn_local(async move { handle.await * 2 });
+
+        assert!(!joined.is_finished());
+
+        while run_ready() {}
+
+        assert!(joined.is_finished());
+        assert_eq!(joined.try_take(), Some(10));
+
+        let mut ready = spawn_local(async { 1 }); // polled right away
+        assert_eq!(ready.try_take(), Some(1));
+    }
+}
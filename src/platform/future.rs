@@ -1,17 +1,115 @@
 
-use std::future::Future;
+use std::{future::Future, pin::Pin, rc::Rc, cell::RefCell};
+use std::task::{Context, Poll, Waker};
 
 
-pub fn spawn_local<F: Future<Output = ()> + 'static>(future: F) {
-    #[cfg(not(target_family="wasm"))] pollster::block_on(future);
-    #[cfg(target_family="wasm")] wasm_bindgen_futures::spawn_local(future);
+// handle to the output of a spawned future, dropping it detaches the task
+
+#[derive(Debug)]
+struct JoinState<T> {
+    output: Option<T>,
+    finished: bool,
+    waker: Option<Waker>,
+}
+
+#[derive(Debug)]
+pub struct JoinHandle<T> {
+    state: Rc<RefCell<JoinState<T>>>,
+}
+
+impl<T> JoinHandle<T> {
+
+    pub fn is_finished(&self) -> bool {
+        self.state.borrow().finished
+    }
+
+    // takes the output if the task is finished
+    pub fn try_take(&mut self) -> Option<T> {
+        self.state.borrow_mut().output.take()
+    }
 }
 
+impl<T> Future for JoinHandle<T> {
+    type Output = T;
 
-pub trait SpawnFutureLocal: Future<Output = ()> + 'static {
-    fn spawn_local(self);
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut state = self.state.borrow_mut();
+        match state.output.take() {
+            Some(output) => Poll::Ready(output),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            },
+        }
+    }
 }
 
-impl<F: Future<Output = ()> + 'static> SpawnFutureLocal for F {
-    fn spawn_local(self) { spawn_local(self) }
+
+pub fn spawn_local<F: Future + 'static>(future: F) -> JoinHandle<F::Output> {
+
+    let state = Rc::new(RefCell::new(JoinState { output: None, finished: false, waker: None }));
+
+    let task = {
+        let state = Rc::clone(&state);
+        async move {
+            let output = future.await;
+            let waker = {
+                let mut state = state.borrow_mut();
+                state.output = Some(output);
+                state.finished = true;
+                state.waker.take()
+            };
+            if let Some(waker) = waker { waker.wake() }
+        }
+    };
+
+    #[cfg(not(target_family="wasm"))] super::executor::spawn(Box::pin(task));
+    #[cfg(target_family="wasm")] wasm_bindgen_futures::spawn_local(task);
+
+    JoinHandle { state }
+}
+
+
+pub trait SpawnFutureLocal: Future + 'static {
+    fn spawn_local(self) -> JoinHandle<Self::Output>;
+}
+
+impl<F: Future + 'static> SpawnFutureLocal for F {
+    fn spawn_local(self) -> JoinHandle<F::Output> { spawn_local(self) }
+}
+
+
+// runs blocking work like file io on its own thread, the future completes with its result
+
+#[cfg(not(target_family="wasm"))]
+#[cfg(any(feature = "clipboard", feature = "file_drop", feature = "assets"))]
+pub(crate) fn in_background<R: Send + 'static>(work: impl FnOnce() -> R + Send + 'static) -> impl Future<Output=R> {
+
+    use std::{sync::{Arc, Mutex}, future::poll_fn};
+
+    let shared = Arc::new(Mutex::new((None, None::<Waker>)));
+
+    std::thread::spawn({
+        let shared = Arc::clone(&shared);
+        move || {
+            let res = work();
+            let waker = {
+                let mut shared = shared.lock().unwrap();
+                shared.0 = Some(res);
+                shared.1.take()
+            };
+            if let Some(waker) = waker { waker.wake() }
+        }
+    });
+
+    poll_fn(move |cx| {
+        let mut shared = shared.lock().unwrap();
+        match shared.0.take() {
+            Some(res) => Poll::Ready(res),
+            None => {
+                shared.1 = Some(cx.waker().clone());
+                Poll::Pending
+            },
+        }
+    })
 }
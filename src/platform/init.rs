@@ -20,7 +20,7 @@ pub fn init(log_level: LogLevel) {
 }
 
 
-pub fn event_loop() -> PlatformEventLoop {
+pub fn event_loop<T: 'static>() -> PlatformEventLoop<T> {
     EventLoop::with_user_event().build().unwrap()
 }
 
@@ -9,22 +9,65 @@ use winit::event_loop::{
 use winit::event::Event as WinitEventType;
 use winit::window::WindowId;
 
+#[cfg(all(feature = "file_drop", target_family="wasm"))]
+use winit::dpi::PhysicalPosition;
+
 
 #[derive(Debug, Clone, PartialEq)]
-pub enum PlatformEventExt {
+pub enum PlatformEventExt<T = ()> {
     AppInit { window_id: WindowId },
 
-    #[cfg(all(feature = "web_clipboard", target_family="wasm"))]
+    User(T),
+
+    AppWake, // see AppHandler::wake
+
+    #[cfg(not(target_family="wasm"))]
+    Wake, // local tasks are ready
+
+    #[cfg(any(feature = "clipboard", all(feature = "web_clipboard", target_family="wasm")))]
     ClipboardFetch { window_id: WindowId },
 
     #[cfg(all(feature = "web_clipboard", target_family="wasm"))]
     ClipboardPaste { window_id: WindowId },
+
+    #[cfg(all(feature = "web_clipboard", target_family="wasm"))]
+    ClipboardCopy { window_id: WindowId },
+
+    #[cfg(all(feature = "web_clipboard", target_family="wasm"))]
+    ClipboardCut { window_id: WindowId },
+
+    #[cfg(all(feature = "web_clipboard", target_family="wasm", web_sys_unstable_apis))]
+    ClipboardError { window_id: WindowId, error: crate::web_clipboard::ClipboardError },
+
+    #[cfg(all(feature = "file_drop", target_family="wasm"))]
+    FileHoverEntered { window_id: WindowId, position: Option<PhysicalPosition<f64>> },
+
+    #[cfg(all(feature = "file_drop", target_family="wasm"))]
+    FileHoverLeft { window_id: WindowId },
+
+    #[cfg(all(feature = "file_drop", target_family="wasm"))]
+    FilesDropped { window_id: WindowId, files: Vec<crate::DroppedFile>, position: Option<PhysicalPosition<f64>> },
 }
 
-pub type PlatformEventLoop = WinitEventLoop<PlatformEventExt>;
-pub type PlatformEventLoopProxy = WinitEventLoopProxy<PlatformEventExt>;
-pub type PlatformEvent = WinitEventType<PlatformEventExt>;
-pub type PlatformEventLoopClosed = WinitEventLoopClosed<PlatformEventExt>;
+pub type PlatformEventLoop<T = ()> = WinitEventLoop<PlatformEventExt<T>>;
+pub type PlatformEventLoopProxy<T = ()> = WinitEventLoopProxy<PlatformEventExt<T>>;
+pub type PlatformEvent<T = ()> = WinitEventType<PlatformEventExt<T>>;
+pub type PlatformEventLoopClosed<T = ()> = WinitEventLoopClosed<PlatformEventExt<T>>;
+
+
+// user events may be sent from other threads on native
+
+#[cfg(not(target_family="wasm"))]
+pub trait UserEvent: Send + 'static {}
+
+#[cfg(not(target_family="wasm"))]
+impl<T: Send + 'static> UserEvent for T {}
+
+#[cfg(target_family="wasm")]
+pub trait UserEvent: 'static {}
+
+#[cfg(target_family="wasm")]
+impl<T: 'static> UserEvent for T {}
 
 
 // submods
@@ -32,6 +75,12 @@ pub type PlatformEventLoopClosed = WinitEventLoopClosed<PlatformEventExt>;
 mod future;
 pub use future::*;
 
+#[cfg(not(target_family="wasm"))]
+pub(crate) mod executor;
+
+mod sleep;
+pub use sleep::*;
+
 mod conditional_execution;
 pub use conditional_execution::*;
 
@@ -0,0 +1,193 @@
+
+// async timers, on native they need to be polled on the event loop thread
+
+use std::{future::{Future, poll_fn}, pin::{Pin, pin}, fmt, error::Error};
+use std::task::{Context, Poll};
+use crate::time::{Instant, Duration};
+
+#[cfg(not(target_family="wasm"))]
+use super::executor::{self, TimerKey};
+
+#[cfg(target_family="wasm")]
+use {std::{rc::Rc, cell::RefCell, task::Waker}, wasm_bindgen::prelude::*};
+
+
+#[cfg(target_family="wasm")]
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_name = setTimeout)]
+    fn set_timeout(callback: &JsValue, millis: i32) -> i32;
+
+    #[wasm_bindgen(js_name = clearTimeout)]
+    fn clear_timeout(id: i32);
+}
+
+
+#[cfg(target_family="wasm")]
+#[derive(Debug, Default)]
+struct TimeoutState {
+    fired: bool,
+    waker: Option<Waker>,
+}
+
+#[cfg(target_family="wasm")]
+#[derive(Debug)]
+struct WebTimeout {
+    id: i32,
+    state: Rc<RefCell<TimeoutState>>,
+    _callback: Closure<dyn FnMut()>,
+}
+
+#[cfg(target_family="wasm")]
+impl WebTimeout {
+    fn new(duration: Duration, waker: &Waker) -> Self {
+
+        let state = Rc::new(RefCell::new(TimeoutState { fired: false, waker: Some(waker.clone()) }));
+
+        let callback = {
+            let state = Rc::clone(&state);
+            Closure::<dyn FnMut()>::new(move || {
+                let waker = {
+                    let mut state = state.borrow_mut();
+                    state.fired = true;
+                    state.waker.take()
+                };
+                if let Some(waker) = waker { waker.wake() }
+            })
+        };
+
+        // round up to whole millis
+        let millis = duration.as_micros().div_ceil(1000);
+        let id = set_timeout(callback.as_ref(), millis.min(i32::MAX as u128) as i32);
+
+        Self { id, state, _callback: callback }
+    }
+}
+
+#[cfg(target_family="wasm")]
+impl Drop for WebTimeout {
+    fn drop(&mut self) {
+        if !self.state.borrow().fired { clear_timeout(self.id) }
+    }
+}
+
+
+#[derive(Debug)]
+pub struct Sleep {
+    deadline: Instant,
+    #[cfg(not(target_family="wasm"))] timer: Option<TimerKey>,
+    #[cfg(target_family="wasm")] timeout: Option<WebTimeout>,
+}
+
+impl Sleep {
+    pub fn deadline(&self) -> Instant { self.deadline }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+
+        let this = self.get_mut();
+        let now = Instant::now();
+
+        if this.deadline <= now {
+            return Poll::Ready(());
+        }
+
+        #[cfg(not(target_family="wasm"))] {
+            this.timer = Some(executor::register_timer(this.timer, this.deadline, cx.waker()));
+        }
+
+        #[cfg(target_family="wasm")]
+        match &this.timeout {
+            Some(timeout) => {
+                let mut state = timeout.state.borrow_mut();
+                if state.fired { return Poll::Ready(()) }
+                state.waker = Some(cx.waker().clone());
+            },
+            None => {
+                this.timeout = Some(WebTimeout::new(this.deadline - now, cx.waker()));
+            },
+        }
+
+        Poll::Pending
+    }
+}
+
+#[cfg(not(target_family="wasm"))]
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        if let Some(key) = self.timer.take() { executor::cancel_timer(key) }
+    }
+}
+
+
+pub fn sleep_until(deadline: Instant) -> Sleep {
+    Sleep {
+        deadline,
+        #[cfg(not(target_family="wasm"))] timer: None,
+        #[cfg(target_family="wasm")] timeout: None,
+    }
+}
+
+pub fn sleep(duration: Duration) -> Sleep {
+    sleep_until(Instant::now() + duration)
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Elapsed;
+
+impl fmt::Display for Elapsed {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "future has timed out")
+    }
+}
+
+impl Error for Elapsed {}
+
+
+pub async fn timeout<F: Future>(future: F, duration: Duration) -> Result<F::Output, Elapsed> {
+
+    let mut future = pin!(future);
+    let mut sleep = pin!(sleep(duration));
+
+    poll_fn(|cx| {
+        if let Poll::Ready(output) = future.as_mut().poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+        sleep.as_mut().poll(cx).map(|()| Err(Elapsed))
+    }).await
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use crate::{spawn_local, time::{Instant, Duration}};
+    use super::{sleep, timeout, Elapsed, executor};
+
+    #[test]
+    fn sleep_and_timeout() {
+
+        let start = Instant::now();
+
+        let mut slept = spawn_local(async { sleep(Duration::from_millis(2)).await; Instant::now() });
+        let mut timed_out = spawn_local(timeout(sleep(Duration::from_secs(60)), Duration::from_millis(1)));
+        let mut in_time = spawn_local(timeout(async { 3 }, Duration::from_millis(1)));
+
+        assert_eq!(in_time.try_take(), Some(Ok(3)));
+        assert!(executor::next_timer().is_some());
+
+        while let Some(deadline) = executor::next_timer().filter(|deadline| *deadline < start + Duration::from_secs(1)) {
+            std::thread::sleep(deadline.saturating_duration_since(Instant::now()));
+            executor::fire_timers(Instant::now());
+            executor::run_ready();
+        }
+
+        assert_eq!(timed_out.try_take(), Some(Err(Elapsed)));
+        assert!(slept.try_take().is_some_and(|instant| instant >= start + Duration::from_millis(2)));
+        assert_eq!(executor::next_timer(), None); // dropped sleep is cancelled
+    }
+}
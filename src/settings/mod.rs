@@ -0,0 +1,183 @@
+
+use serde::{Serialize, de::DeserializeOwned};
+use serde_json::{Map, Value};
+use anyhow::{Result as Res, bail};
+use crate::*;
+
+#[cfg(not(target_family="wasm"))]
+mod native;
+
+#[cfg(not(target_family="wasm"))]
+use {native::*, std::path::PathBuf};
+
+
+#[cfg(target_family="wasm")]
+mod web;
+
+#[cfg(target_family="wasm")]
+use web::*;
+
+
+// where the settings are persisted
+
+#[derive(Debug, Clone, Default, PartialEq)]
+enum Storage {
+    #[default] Memory,
+    #[cfg(not(target_family="wasm"))] File(PathBuf),
+    #[cfg(target_family="wasm")] LocalStorage(String),
+}
+
+impl Storage {
+
+    fn read(&self) -> Res<Option<String>> {
+        match self {
+            Storage::Memory => Ok(None),
+            #[cfg(not(target_family="wasm"))] Storage::File(path) => read_file(path),
+            #[cfg(target_family="wasm")] Storage::LocalStorage(key) => read_local_storage(key),
+        }
+    }
+
+    fn write(&self, data: &str) -> Res<()> {
+        match self {
+            Storage::Memory => Ok(()),
+            #[cfg(not(target_family="wasm"))] Storage::File(path) => write_file_atomic(path, data),
+            #[cfg(target_family="wasm")] Storage::LocalStorage(key) => write_local_storage(key, data),
+        }
+    }
+}
+
+
+// typed key-value store, values are stored as json
+// AppCtx::settings is in memory until replaced with a loaded one, AppState saves changes once they settle and on exit
+
+#[derive(Debug, Default)]
+pub struct Settings {
+    values: Map<String, Value>,
+    storage: Storage,
+    changes: Vec<String>,
+    unsaved: bool,
+}
+
+impl Settings {
+
+    // $XDG_CONFIG_HOME/<app_id>/settings.json on linux, localStorage key <app_id>/settings on web
+    pub fn load(app_id: &str) -> Res<Self> {
+
+        if app_id.is_empty() || app_id.contains(['/', '\\']) || app_id.starts_with('.') {
+            bail!("invalid app id {app_id:?}");
+        }
+
+        #[cfg(not(target_family="wasm"))] { Self::from_storage(Storage::File(config_file(app_id)?)) }
+        #[cfg(target_family="wasm")] { Self::from_storage(Storage::LocalStorage(format!("{app_id}/settings"))) }
+    }
+
+    #[cfg(not(target_family="wasm"))]
+    pub fn load_file(path: impl Into<PathBuf>) -> Res<Self> {
+        Self::from_storage(Storage::File(path.into()))
+    }
+
+    fn from_storage(storage: Storage) -> Res<Self> {
+        let values = match storage.read()? {
+            Some(json) => serde_json::from_str(&json)?,
+            None => Map::new(),
+        };
+        Ok(Self { values, storage, changes: Vec::new(), unsaved: false })
+    }
+
+    pub fn is_persistent(&self) -> bool {
+        self.storage != Storage::Memory
+    }
+
+    // None if missing or of another type
+    pub fn get<V: DeserializeOwned>(&self, key: &str) -> Option<V> {
+        let value = self.values.get(key)?;
+        V::deserialize(value).inspect_err(|err| log_warn!(err)).ok()
+    }
+
+    pub fn get_or<V: DeserializeOwned>(&self, key: &str, default: V) -> V {
+        self.get(key).unwrap_or(default)
+    }
+
+    pub fn set<V: Serialize>(&mut self, key: &str, value: V) -> Res<()> {
+        let value = serde_json::to_value(value)?;
+        if self.values.get(key) != Some(&value) {
+            self.values.insert(key.to_string(), value);
+            self.changed(key);
+        }
+        Ok(())
+    }
+
+    pub fn remove(&mut self, key: &str) -> bool {
+        let removed = self.values.remove(key).is_some();
+        if removed { self.changed(key) }
+        removed
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.values.contains_key(key)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item=&str> {
+        self.values.keys().map(String::as_str)
+    }
+
+    fn changed(&mut self, key: &str) {
+        self.unsaved = true;
+        if !self.changes.iter().any(|changed| changed == key) {
+            self.changes.push(key.to_string());
+        }
+    }
+
+    pub fn has_unsaved(&self) -> bool {
+        self.unsaved
+    }
+
+    pub fn save(&mut self) -> Res<()> {
+        if self.unsaved {
+            self.storage.write(&serde_json::to_string_pretty(&self.values)?)?;
+            self.unsaved = false;
+        }
+        Ok(())
+    }
+
+    // keys changed since the last call, delivered as AppEvent::SettingsChanged
+    pub fn take_changes(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.changes)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use serde::{Serialize, Deserialize};
+    use super::Settings;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Volume { music: f32, muted: bool }
+
+    #[test]
+    fn typed_values_and_file() {
+
+        let path = std::env::temp_dir().join(format!("platform-settings-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut settings = Settings::load_file(&path).unwrap();
+        assert_eq!(settings.get::<u32>("missing"), None);
+
+        settings.set("scale", 2).unwrap();
+        settings.set("volume", Volume { music: 0.5, muted: false }).unwrap();
+        settings.set("scale", 2).unwrap(); // unchanged
+        assert_eq!(settings.take_changes(), ["scale", "volume"]);
+        assert_eq!(settings.get::<String>("scale"), None); // other type
+
+        settings.save().unwrap();
+        assert!(!settings.has_unsaved());
+
+        let loaded = Settings::load_file(&path).unwrap();
+        assert_eq!(loaded.get_or("scale", 1), 2);
+        assert_eq!(loaded.get("volume"), Some(Volume { music: 0.5, muted: false }));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,51 @@
+use std::{path::{Path, PathBuf}, fs, io::{ErrorKind, Write}, env};
+use anyhow::{Result as Res, Context};
+
+
+fn config_dir() -> Res<PathBuf> {
+
+    #[cfg(target_os = "windows")]
+    { env::var_os("APPDATA").map(PathBuf::from).context("APPDATA is not set") }
+
+    #[cfg(target_os = "macos")]
+    { env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support")).context("HOME is not set") }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        env::var_os("XDG_CONFIG_HOME").map(PathBuf::from).filter(|path| path.is_absolute())
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .context("neither XDG_CONFIG_HOME nor HOME is set")
+    }
+}
+
+pub(super) fn config_file(app_id: &str) -> Res<PathBuf> {
+    Ok(config_dir()?.join(app_id).join("settings.json"))
+}
+
+
+pub(super) fn read_file(path: &Path) -> Res<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(data) => Ok(Some(data)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err).with_context(|| format!("couldn't read {}", path.display())),
+    }
+}
+
+// write to a temporary file and rename it, so the settings file is never partially written
+pub(super) fn write_file_atomic(path: &Path, data: &str) -> Res<()> {
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("couldn't create {}", dir.display()))?;
+    }
+
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+
+    let mut file = fs::File::create(&tmp_path).with_context(|| format!("couldn't create {}", tmp_path.display()))?;
+    file.write_all(data.as_bytes())?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&tmp_path, path).with_context(|| format!("couldn't replace {}", path.display()))
+}
@@ -0,0 +1,18 @@
+use anyhow::{Result as Res, Context, anyhow};
+use web_sys::Storage;
+
+
+fn local_storage() -> Res<Storage> {
+    web_sys::window().context("couldn't get web_sys::Window")?
+        .local_storage().map_err(|err| anyhow!("{err:?}"))?
+        .context("localStorage is not available")
+}
+
+pub(super) fn read_local_storage(key: &str) -> Res<Option<String>> {
+    local_storage()?.get_item(key).map_err(|err| anyhow!("{err:?}"))
+}
+
+// a single setItem, which replaces the value at once
+pub(super) fn write_local_storage(key: &str, data: &str) -> Res<()> {
+    local_storage()?.set_item(key, data).map_err(|err| anyhow!("{err:?}"))
+}
@@ -1,25 +1,88 @@
 
+use std::{collections::VecDeque, sync::{Arc, Mutex}, fmt::Debug};
 use crate::time::{Instant, Duration};
 
 
+// source of the current time, a VirtualClock makes time dependent code testable
+
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now(&self) -> Instant { (**self).now() }
+}
+
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant { Instant::now() }
+}
+
+
+// only advances manually, clones share the same time
 #[derive(Debug, Clone)]
-pub struct NormInterval {
+pub struct VirtualClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl Default for VirtualClock {
+    fn default() -> Self { Self::new() }
+}
+
+impl VirtualClock {
+
+    pub fn new() -> Self {
+        Self::starting_at(Instant::now())
+    }
+
+    pub fn starting_at(instant: Instant) -> Self {
+        Self { now: Arc::new(Mutex::new(instant)) }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+
+    pub fn set(&self, instant: Instant) {
+        *self.now.lock().unwrap() = instant;
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> Instant { *self.now.lock().unwrap() }
+}
+
+
+
+#[derive(Debug, Clone)]
+pub struct NormInterval<C: Clock = SystemClock> {
   pub instant: Instant,
   pub duration: Duration,
+  pub clock: C,
 }
 
 impl NormInterval {
 
     pub fn new(duration: Duration) -> Self {
-        Self { instant: Instant::now(), duration }
+        Self::with_clock(duration, SystemClock)
     }
 
     pub fn from_secs(duration_secs: f64) -> Self {
         Self::new(Duration::from_secs_f64(duration_secs))
     }
+}
+
+impl<C: Clock> NormInterval<C> {
+
+    pub fn with_clock(duration: Duration, clock: C) -> Self {
+        Self { instant: clock.now(), duration, clock }
+    }
 
     pub fn elapsed(&self) -> f64 {
-        let now = Instant::now();
+        let now = self.clock.now();
         if now >= self.instant {
             (now - self.instant).as_secs_f64() / self.duration.as_secs_f64()
         } else {
@@ -51,23 +114,31 @@ impl NormInterval {
 
 
 #[derive(Debug, Clone)]
-pub struct StepInterval {
+pub struct StepInterval<C: Clock = SystemClock> {
     pub next: Instant,
     pub duration: Duration,
+    pub clock: C,
 }
 
 impl StepInterval {
 
     pub fn new(duration: Duration) -> Self {
-        Self { next: Instant::now() + duration, duration }
+        Self::with_clock(duration, SystemClock)
     }
 
     pub fn from_secs(duration_secs: f64) -> Self {
         Self::new(Duration::from_secs_f64(duration_secs))
     }
+}
+
+impl<C: Clock> StepInterval<C> {
+
+    pub fn with_clock(duration: Duration, clock: C) -> Self {
+        Self { next: clock.now() + duration, duration, clock }
+    }
 
     pub fn elapsed(&self) -> i64 {
-        let now = Instant::now();
+        let now = self.clock.now();
         if now >= self.next {
             ((now - self.next).as_nanos() / self.duration.as_nanos()) as i64 + 1
         } else {
@@ -104,9 +175,9 @@ impl StepInterval {
 
 
 #[derive(Debug, Clone)]
-pub struct IntervalCounter {
+pub struct IntervalCounter<C: Clock = SystemClock> {
     pub count: usize,
-    pub interval: StepInterval,
+    pub interval: StepInterval<C>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -118,12 +189,19 @@ pub struct IntervalCount {
 impl IntervalCounter {
 
     pub fn new(duration: Duration) -> Self {
-        Self { count: 0, interval: StepInterval::new(duration) }
+        Self::with_clock(duration, SystemClock)
     }
 
     pub fn from_secs(duration_secs: f64) -> Self {
         Self::new(Duration::from_secs_f64(duration_secs))
     }
+}
+
+impl<C: Clock> IntervalCounter<C> {
+
+    pub fn with_clock(duration: Duration, clock: C) -> Self {
+        Self { count: 0, interval: StepInterval::with_clock(duration, clock) }
+    }
 
     pub fn add(&mut self) {
         self.count += 1;
@@ -143,4 +221,135 @@ impl IntervalCounter {
         }
         else { None }
     }
-}
\ No newline at end of file
+}
+
+
+
+// rolling statistics over the last frames
+// a frame counts as missed when it took more than 1.5 times the target duration
+
+#[derive(Debug, Clone)]
+pub struct FrameStats {
+    deltas: VecDeque<Duration>,
+    capacity: usize,
+    last: Option<Instant>,
+    frames: u64,
+    missed: u64,
+}
+
+impl Default for FrameStats {
+    fn default() -> Self { Self::new(120) }
+}
+
+impl FrameStats {
+
+    pub fn new(capacity: usize) -> Self {
+        Self { deltas: VecDeque::with_capacity(capacity), capacity: capacity.max(1), last: None, frames: 0, missed: 0 }
+    }
+
+    // records a frame at the instant, the first frame after a pause has no delta
+    pub fn frame(&mut self, instant: Instant, target: Duration) {
+        if let Some(last) = self.last.replace(instant) {
+            self.add(instant.saturating_duration_since(last), target);
+        }
+    }
+
+    pub fn add(&mut self, delta: Duration, target: Duration) {
+        if self.deltas.len() == self.capacity { self.deltas.pop_front(); }
+        self.deltas.push_back(delta);
+        self.frames += 1;
+        if delta > target + target / 2 { self.missed += 1 }
+    }
+
+    // the time until the next frame is not counted
+    pub fn pause(&mut self) {
+        self.last = None;
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new(self.capacity);
+    }
+
+    pub fn delta(&self) -> Option<Duration> {
+        self.deltas.back().copied()
+    }
+
+    pub fn fps(&self) -> f64 {
+        let total: Duration = self.deltas.iter().sum();
+        if total.is_zero() { 0.0 } else { self.deltas.len() as f64 / total.as_secs_f64() }
+    }
+
+    pub fn min(&self) -> Option<Duration> {
+        self.deltas.iter().min().copied()
+    }
+
+    pub fn max(&self) -> Option<Duration> {
+        self.deltas.iter().max().copied()
+    }
+
+    // nearest rank, percentile in 0.0..=100.0
+    pub fn percentile(&self, percentile: f64) -> Option<Duration> {
+        let mut sorted: Vec<Duration> = self.deltas.iter().copied().collect();
+        sorted.sort_unstable();
+        let rank = (percentile.clamp(0.0, 100.0) / 100.0 * sorted.len() as f64).ceil() as usize;
+        sorted.get(rank.saturating_sub(1)).copied()
+    }
+
+    pub fn p95(&self) -> Option<Duration> { self.percentile(95.0) }
+
+    pub fn p99(&self) -> Option<Duration> { self.percentile(99.0) }
+
+    // totals since creation or reset
+    pub fn frames(&self) -> u64 { self.frames }
+
+    pub fn missed(&self) -> u64 { self.missed }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use crate::time::Duration;
+    use super::{FrameStats, VirtualClock, StepInterval, IntervalCounter};
+
+    #[test]
+    fn virtual_clock_intervals() {
+
+        let clock = VirtualClock::new();
+        let second = Duration::from_secs(1);
+
+        let mut interval = StepInterval::with_clock(second, clock.clone());
+        assert_eq!(interval.elapsed(), -1);
+
+        clock.advance(second * 3 + second / 2);
+        assert_eq!(interval.step_if_elapsed(), 3);
+        assert_eq!(interval.elapsed(), 0);
+
+        let mut counter = IntervalCounter::with_clock(second, clock.clone());
+        counter.add();
+        counter.add();
+        assert!(counter.count().is_none());
+
+        clock.advance(second);
+        let count = counter.count().unwrap();
+        assert_eq!((count.count, count.times_per_sec), (2, 2.0));
+    }
+
+    #[test]
+    fn frame_stats() {
+
+        let target = Duration::from_millis(10);
+        let mut stats = FrameStats::new(100);
+
+        for millis in 1..=100 { stats.add(Duration::from_millis(millis), target); }
+        stats.add(Duration::from_millis(101), target); // drops the first
+
+        assert_eq!(stats.delta(), Some(Duration::from_millis(101)));
+        assert_eq!(stats.min(), Some(Duration::from_millis(2)));
+        assert_eq!(stats.max(), Some(Duration::from_millis(101)));
+        assert_eq!(stats.p95(), Some(Duration::from_millis(96)));
+        assert_eq!(stats.p99(), Some(Duration::from_millis(100)));
+        assert_eq!(stats.frames(), 101);
+        assert_eq!(stats.missed(), 86); // above 15ms
+    }
+}